@@ -1,25 +1,62 @@
-use jw_internals::{notebooks, Error, Notebook};
+use chrono::{Datelike, NaiveDate};
+use jw_internals::{
+    attachments::Attachment,
+    calendar,
+    config::{self, Color},
+    editor, export, fields, fsck, history, import, notebooks, review,
+    scratch::{self, Scratch},
+    stats, tasks, templates, trash, undo, Context, Error, Notebook, Post,
+};
 
 use std::{
-    env, fs, path,
+    env, fs,
+    io::{self, IsTerminal, Write},
+    path,
     process::{self, ExitCode},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-const DEFAULT_EDITOR: &str = "vi";
+mod repl;
+mod tui;
+
+const DEFAULT_FEED_SIZE: usize = 20;
+
+/// Whether errors are printed in color
+static COLOR: AtomicBool = AtomicBool::new(false);
 
 fn main() -> ExitCode {
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<String> = env::args().skip(1).collect();
 
     if args.is_empty() {
-        eprintln!("Usage: jw [subcommand]");
-        return ExitCode::FAILURE;
+        return usage("jw [subcommand]");
     }
 
-    let notebook = if let Some(id) = args.get(1) {
-        Notebook::load(id)
-    } else {
-        None
+    let config = match config::get() {
+        Ok(config) => config,
+        // A broken configuration can still be fixed with jw config
+        Err(e) if matches!(args[0].as_str(), "config" | "help" | "version") => {
+            eprintln!("Warning: {}", e);
+            config::Config::default()
+        }
+        Err(e) => return fail(&e),
     };
+    let color = match config.color {
+        Color::Always => true,
+        Color::Never => false,
+        Color::Auto => io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+    };
+    COLOR.store(color, Ordering::Relaxed);
+
+    if let Some(id) = &config.default_notebook {
+        add_default_notebook(&mut args, id);
+    }
+    let defaults = config.default_args(&args[0]);
+    add_default_args(&mut args, defaults);
+
+    // `jw post NOTEBOOK --quick` is another name for `jw repl NOTEBOOK`
+    if args[0] == "post" && take_flag(&mut args, "--quick") {
+        args[0] = "repl".to_string();
+    }
 
     match args[0].as_str() {
         "version" => {
@@ -33,231 +70,1668 @@ A micro-journaling tool
   help                     display this help text
   version                  display version information
 
-  new     NOTEBOOK         make a new notebook
-  post    NOTEBOOK         write a post
+  new     NOTEBOOK [--git] [--format FORMAT]
+                           make a new notebook, optionally tracked with git or
+                           for posts in org, asciidoc or text instead of markdown
+  post    NOTEBOOK [--template NAME] [--attach FILE]...
+                           write a post, optionally starting from a template
+                           or with files attached
+  repl    NOTEBOOK         write a post for each line typed, also post --quick
   search  NOTEBOOK  QUERY  list posts containing query
   tags    NOTEBOOK         list hashtags in a notebook
   edit    NOTEBOOK  [NUM]  edit last NUM post
+  delete  NOTEBOOK  [NUM]  move last NUM post to the trash
+  trash   [list|restore NUM|empty [--yes]]
+                           show, restore or empty deleted posts and notebooks
+  undo                     take back the last post or deletion
+  history NOTEBOOK POST    list earlier versions of a post
+  diff    NOTEBOOK POST [REV] [REV]
+                           show changes between two versions of a post
+  restore NOTEBOOK POST REV
+                           bring back an earlier version of a post
+  tui                      browse, search, write and tag posts in the terminal
   path    NOTEBOOK         returns the path of the notebook
-  list                     list existing notebooks"#
+  fsck    NOTEBOOK         report missing posts and missing or unused attachments
+  list    [--all]          list existing notebooks, with --all also archived ones
+  notebook info|rename|name|describe|archive|unarchive|delete NOTEBOOK [VALUE]
+                           show details about a notebook or change it
+  config  [list|get KEY|set KEY VALUE|unset KEY]
+                           show or change settings in config.toml
+  draft   NOTEBOOK [list|edit NUM|publish NUM|delete NUM]
+                           write a draft, or manage saved drafts
+  recover [list|resume NUM|discard NUM]
+                           recover posts left unsaved after a crash
+  calendar NOTEBOOK [YEAR-MONTH|YEAR-MONTH-DAY]
+                           show the days of a month with posts, or a day's posts
+  onthisday NOTEBOOK       show posts from this day in earlier years
+  random  NOTEBOOK [--tag TAG]
+                           show a random post, optionally with a tag
+  review  NOTEBOOK [--new NUM]
+                           re-read posts on a spaced-repetition schedule
+  stats   NOTEBOOK [--json]
+                           show word counts, streaks and when you write
+  tasks   NOTEBOOK [--open]
+                           list the checklist items in posts
+  tasks   done NOTEBOOK ID check off a task in its post
+  track   NOTEBOOK [FIELD] [--since DATE|30d] [--csv|--sparkline]
+                           show the values of a field like mood:: 4 over time
+  sync    NOTEBOOK         pull and push a git notebook to its remote
+  encrypt NOTEBOOK [--key-file PATH]
+                           encrypt a notebook with a passphrase or key file
+  decrypt NOTEBOOK         store an encrypted notebook as plain text again
+
+  export  html NOTEBOOK OUT_DIR [TEMPLATES]
+                           export a notebook as a static site
+  export  feed NOTEBOOK [COUNT] [--rss]
+                           print an Atom (or RSS) feed of the latest posts
+  export  markdown|json|epub NOTEBOOK OUT_FILE [--from DATE] [--to DATE]
+                           export posts into a single document
+
+  import  FORMAT SOURCE NOTEBOOK
+                           import posts from jrnl, dayone, nb or a dir"#
             );
         }
 
         // Notebook-related subcommands
         "new" => {
+            let git = take_flag(&mut args, "--git");
+            let format = match take_option(&mut args, "--format").map(|f| f.parse()) {
+                None => None,
+                Some(Ok(format)) => Some(format),
+                Some(Err(e)) => {
+                    eprintln!("Error: {}", e);
+                    return usage("jw new NOTEBOOK [--git] [--format FORMAT]");
+                }
+            };
             if args.len() < 2 {
-                eprintln!("Usage: jw new NOTEBOOK");
-                return ExitCode::FAILURE;
+                return usage("jw new NOTEBOOK [--git] [--format FORMAT]");
             }
 
             let id = &args[1];
-            match Notebook::generate(id) {
-                Ok(_) => {}
+            let mut config = notebooks::Config {
+                git,
+                format,
+                ..notebooks::Config::new(id)
+            };
+            if let Some(format) = format {
+                config.post_path = format!("%Y-%m-%d-%s{}", format.extension());
+            }
+            if let Err(e) = Notebook::generate_with(id, config) {
+                return fail(&e);
+            }
+        }
+
+        "repl" => {
+            if args.len() < 2 {
+                return usage("jw repl NOTEBOOK");
+            }
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            if let Err(e) = repl::run(&notebook) {
+                return fail(&e);
+            }
+        }
+
+        "post" => {
+            let template = take_option(&mut args, "--template");
+            let mut files = Vec::new();
+            while let Some(file) = take_option(&mut args, "--attach") {
+                files.push(file);
+            }
+            if args.len() < 2 {
+                return usage("jw post NOTEBOOK [--template NAME] [--attach FILE]...");
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let notebook_config = match notebook.read_config() {
+                Ok(config) => config,
+                Err(e) => return fail(&e),
+            };
+            let ext = notebook_config.extension();
+            let format = notebook_config.post_format();
+            let date_format = notebook_config.date_format.unwrap_or(config.date_format);
+
+            // Recover text left behind by a crashed editor before starting anew
+            let recovered = match scratch::list(Some(&notebook.id)) {
+                Ok(unsaved) => unsaved.into_iter().last().filter(|scratch| {
+                    confirm(&format!(
+                        "You have an unsaved post from {}. Resume it?",
+                        scratch.created.format(&date_format)
+                    ))
+                }),
+                Err(_) => None,
+            };
+            if let Some(scratch) = recovered {
+                return post_from_scratch(&notebook, scratch, None, &[]);
+            }
+
+            let text = match templates::for_post(&notebook, template.as_deref()) {
+                Ok(text) => text,
+                Err(e) => return fail(&e),
+            };
+            // Files are only copied into the notebook once the post is saved
+            let mut links = Vec::new();
+            for file in &files {
+                match Attachment::for_file(path::Path::new(file)) {
+                    Ok(attachment) => links.push(attachment.link(format)),
+                    Err(e) => return fail(&e),
+                }
+            }
+            // Links to attachments go below the template, leaving the first line to write on
+            let start = match (&text, links.is_empty()) {
+                (_, true) => text.clone(),
+                (Some(text), false) => {
+                    Some(format!("{}\n\n{}\n", text.trim_end(), links.join("\n")))
+                }
+                (None, false) => Some(format!("\n\n{}\n", links.join("\n"))),
+            };
+            let scratch = match Scratch::create(&notebook.id, &ext) {
+                Ok(scratch) => scratch,
                 Err(e) => {
-                    if let Error::Exists = e {
-                        eprintln!("jw: notebook {id} already exists");
+                    eprintln!("Failed to create temporary file: {}", e);
+                    return exit_code(&e);
+                }
+            };
+            if let Some(start) = &start {
+                if let Err(e) = scratch.write(start) {
+                    eprintln!("Failed to write template: {}", e);
+                    return exit_code(&e);
+                }
+            }
+
+            return post_from_scratch(&notebook, scratch, start.as_deref(), &files);
+        }
+
+        "list" => {
+            let notebooks = if take_flag(&mut args, "--all") {
+                notebooks::list_all()
+            } else {
+                notebooks::list()
+            };
+            match notebooks {
+                Ok(notebooks) => {
+                    for notebook in notebooks {
+                        println!("{}", notebook.id);
+                    }
+                }
+                Err(e) => return fail(&e),
+            }
+        }
+
+        "notebook" => {
+            let yes = take_flag(&mut args, "--yes");
+            let usage_text =
+                "jw notebook info|rename|name|describe|archive|unarchive|delete NOTEBOOK";
+            let (action, id) = match (args.get(1), args.get(2)) {
+                (Some(action), Some(id)) => (action.as_str(), id),
+                _ => return usage(usage_text),
+            };
+            let value = args[3..].join(" ");
+
+            let result = match action {
+                "info" => {
+                    let notebook = match open(id) {
+                        Ok(notebook) => notebook,
+                        Err(code) => return code,
+                    };
+                    notebook
+                        .info()
+                        .map(|info| print_info(&notebook, &info, &config))
+                }
+                "delete" => {
+                    let notebook = match load(id) {
+                        Ok(notebook) => notebook,
+                        Err(code) => return code,
+                    };
+                    if !yes
+                        && !confirm(&format!(
+                            "Move {} and all its posts to the trash?",
+                            notebook.id
+                        ))
+                    {
+                        return ExitCode::SUCCESS;
+                    }
+                    notebook
+                        .delete()
+                        .map(|_| println!("Moved {} to the trash. Undo with jw undo", id))
+                }
+                "rename" | "name" | "describe" | "archive" | "unarchive" => {
+                    let mut notebook = match load(id) {
+                        Ok(notebook) => notebook,
+                        Err(code) => return code,
+                    };
+                    match action {
+                        "rename" | "name" if value.is_empty() => {
+                            return usage(&format!("jw notebook {} NOTEBOOK NAME", action))
+                        }
+                        "rename" => notebook.rename(&value),
+                        "name" => notebook.set_name(&value),
+                        "describe" => notebook.set_description(&value),
+                        "archive" => notebook.archive(true),
+                        _ => notebook.archive(false),
+                    }
+                }
+                _ => return usage(usage_text),
+            };
+            if let Err(e) = result {
+                return fail(&e);
+            }
+        }
+
+        "config" => match args.get(1).map(|s| s.as_str()) {
+            None | Some("list") => {
+                for (key, value) in config.list() {
+                    println!("{} = {}", key, value);
+                }
+            }
+            Some("get") => {
+                let key = match args.get(2) {
+                    Some(key) => key,
+                    None => return usage("jw config get KEY"),
+                };
+                match config.get(key) {
+                    Some(value) => match value.as_str() {
+                        Some(value) => println!("{}", value),
+                        None => println!("{}", value),
+                    },
+                    None => {
+                        eprintln!("Error: {} is not set", key);
                         return ExitCode::FAILURE;
                     }
-                    eprintln!("Error: {}", e);
-                    return ExitCode::FAILURE;
                 }
+            }
+            Some("set") => {
+                if args.len() < 4 {
+                    return usage("jw config set KEY VALUE");
+                }
+                if let Err(e) = config::set(&args[2], Some(&args[3..].join(" "))) {
+                    return fail(&e);
+                }
+            }
+            Some("unset") => {
+                if args.len() < 3 {
+                    return usage("jw config unset KEY");
+                }
+                if let Err(e) = config::set(&args[2], None) {
+                    return fail(&e);
+                }
+            }
+            Some(_) => return usage("jw config [list|get KEY|set KEY VALUE|unset KEY]"),
+        },
+
+        "search" => {
+            if args.len() < 3 {
+                return usage("jw search NOTEBOOK QUERY");
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let results = match notebook.search(&args[2]) {
+                Ok(results) => results,
+                Err(e) => return fail(&e),
             };
+            let lines: Vec<String> = results.iter().map(|p| p.display().to_string()).collect();
+            page(&config, &lines.join("\n"));
         }
 
-        "post" => {
+        "tags" => {
+            if args.len() < 2 {
+                return usage("jw tags NOTEBOOK");
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let tags = match notebook.tags() {
+                Ok(tags) => tags,
+                Err(e) => return fail(&e),
+            };
+            let mut tags: Vec<(&String, &usize)> = tags.iter().collect();
+            tags.sort();
+
+            let lines: Vec<String> = tags
+                .iter()
+                .map(|(tag, count)| format!("#{tag}: {count}"))
+                .collect();
+            page(&config, &lines.join("\n"));
+        }
+
+        "path" => {
             if args.len() < 2 {
-                eprintln!("Usage: jw post NOTEBOOK");
+                return usage("jw path NOTEBOOK");
+            }
+
+            let notebook = match load(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            println!("{}", notebook.path.display());
+        }
+
+        "edit" => {
+            if args.len() < 2 {
+                return usage("jw edit NOTEBOOK [NUM]");
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let posts = match notebook.get_posts() {
+                Ok(posts) => posts,
+                Err(e) => return fail(&e),
+            };
+
+            let num = if args.len() < 3 {
+                0
+            } else if let Ok(num) = args[2].parse::<usize>() {
+                num
+            } else {
+                return usage("jw edit NOTEBOOK [NUM]");
+            };
+
+            if let Some(path) = posts.iter().rev().nth(num) {
+                if let Err(e) = history::snapshot(&notebook, path) {
+                    eprintln!("Error saving the current version: {}", e);
+                    return exit_code(&e);
+                }
+                if let Err(e) = edit_file(&notebook, path) {
+                    return fail(&e);
+                }
+                let message = format!("Edit post {}", path.display());
+                if let Err(e) = notebook.commit(&message) {
+                    eprintln!("Error committing changes: {}", e);
+                    return exit_code(&e);
+                }
+            } else {
+                eprintln!("Error: number too large. No such post.");
                 return ExitCode::FAILURE;
             }
+        }
 
-            if let Some(notebook) = notebook {
-                let config = notebook.read_config().unwrap();
-                let post_path = std::path::PathBuf::from(config.post_path);
-                let ext = match post_path
-                    .extension() {
-                        Some(ext) => ".".to_string() + ext.to_str().unwrap(),
-                        None => "".to_owned(),
-                    };
+        "delete" => {
+            let yes = take_flag(&mut args, "--yes");
+            if args.len() < 2 {
+                return usage("jw delete NOTEBOOK [NUM] [--yes]");
+            }
 
-                match get_text(&ext) {
-                    Ok(text) => {
-                        if !text.is_empty() {
-                            match notebook.post(&text) {
-                                Ok(path) => {
-                                    println!("Wrote post to {:?}", path);
-                                }
-                                Err(e) => {
-                                    eprintln!("Error writing post: {}", e);
-                                    return ExitCode::FAILURE;
-                                }
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let posts = match notebook.get_posts() {
+                Ok(posts) => posts,
+                Err(e) => return fail(&e),
+            };
+            let num = match args.get(2).map(|n| n.parse::<usize>()) {
+                None => 0,
+                Some(Ok(num)) => num,
+                Some(Err(_)) => return usage("jw delete NOTEBOOK [NUM] [--yes]"),
+            };
+
+            let path = match posts.iter().rev().nth(num) {
+                Some(path) => path,
+                None => {
+                    eprintln!("Error: number too large. No such post.");
+                    return ExitCode::FAILURE;
+                }
+            };
+            if !yes && !confirm(&format!("Move {} to the trash?", path.display())) {
+                return ExitCode::SUCCESS;
+            }
+            if let Err(e) = notebook.delete_post(path) {
+                return fail(&e);
+            }
+            println!("Moved {} to the trash. Undo with jw undo", path.display());
+        }
+
+        "trash" => {
+            let yes = take_flag(&mut args, "--yes");
+            let entries = match trash::list() {
+                Ok(entries) => entries,
+                Err(e) => return fail(&e),
+            };
+
+            match args.get(1).map(|s| s.as_str()) {
+                None | Some("list") => {
+                    for (i, entry) in entries.iter().enumerate() {
+                        let origin = &entry.origin;
+                        let item = match origin.kind {
+                            trash::Kind::Post => {
+                                format!("{}/{}", origin.notebook, origin.path.display())
                             }
-                        } else {
-                            eprintln!("Post empty; not saved.");
-                        }
+                            trash::Kind::Notebook => format!("notebook {}", origin.notebook),
+                        };
+                        println!(
+                            "{:<3} {}  {}",
+                            i,
+                            origin.deleted.format(&config.date_format),
+                            item
+                        );
                     }
-                    Err(p) => {
-                        eprintln!("Failed to read temporary file: {}", p.display());
-                        return ExitCode::FAILURE;
+                }
+                Some("restore") => {
+                    let entry = match args.get(2).and_then(|n| n.parse::<usize>().ok()) {
+                        Some(num) if num < entries.len() => entries[num].clone(),
+                        _ => return usage("jw trash restore NUM"),
+                    };
+                    match entry.restore() {
+                        Ok(path) => println!("Restored {}", path.display()),
+                        Err(e) => return fail(&e),
                     }
                 }
-            } else {
-                open_error(&args[1]);
-                return ExitCode::FAILURE;
+                Some("empty") => {
+                    if entries.is_empty()
+                        || !yes && !confirm(&format!("Delete {} items for good?", entries.len()))
+                    {
+                        return ExitCode::SUCCESS;
+                    }
+                    if let Err(e) = trash::empty() {
+                        return fail(&e);
+                    }
+                }
+                Some(_) => return usage("jw trash [list|restore NUM|empty [--yes]]"),
             }
         }
 
-        "list" => match notebooks::list() {
-            Ok(notebooks) => {
-                for notebook in notebooks {
-                    println!("{}", notebook.id);
+        "undo" => {
+            let undone = match undo::undo() {
+                Ok(undone) => undone,
+                Err(e) => return fail(&e),
+            };
+            for operation in undone.skipped {
+                match operation {
+                    undo::Operation::Post { notebook, path } => eprintln!(
+                        "Skipped post {}/{}, which no longer exists",
+                        notebook,
+                        path.display()
+                    ),
+                    undo::Operation::Delete { entry } => {
+                        eprintln!("Skipped deleting {}, which can't be restored", entry)
+                    }
                 }
             }
-            Err(e) => {
-                eprintln!("Failed to open notebooks parent folder: {:?}", e);
-                return ExitCode::FAILURE;
+            match undone.operation {
+                Some(undo::Operation::Post { notebook, path }) => {
+                    println!("Moved post {}/{} to the trash", notebook, path.display());
+                }
+                Some(undo::Operation::Delete { entry }) => println!("Restored {}", entry),
+                None => println!("Nothing to undo"),
             }
-        },
+        }
 
-        "search" => {
+        "history" | "diff" | "restore" => {
+            let usage_text = match args[0].as_str() {
+                "history" => "jw history NOTEBOOK POST",
+                "diff" => "jw diff NOTEBOOK POST [REV] [REV]",
+                _ => "jw restore NOTEBOOK POST REV",
+            };
             if args.len() < 3 {
-                eprintln!("Usage: jw search NOTEBOOK QUERY");
+                return usage(usage_text);
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let post = match find_post(&notebook, &args[2]) {
+                Ok(post) => post,
+                Err(code) => return code,
+            };
+            let revisions = match history::list(&notebook, &post) {
+                Ok(revisions) => revisions,
+                Err(e) => return fail(&e),
+            };
+            if revisions.is_empty() {
+                if notebook.read_config().is_ok_and(|c| c.git) {
+                    eprintln!("{} uses git. See its history with git log", notebook.id);
+                } else {
+                    eprintln!("{} has no earlier versions", post.display());
+                }
                 return ExitCode::FAILURE;
             }
 
-            if let Some(notebook) = notebook {
-                if args.len() < 3 {
-                    eprintln!("Usage: jw search {} QUERY", &args[1]);
+            // Revisions are numbered from the newest, with 0 for the current version
+            let revision = |arg: Option<&String>, default: usize| match arg {
+                None => Some(default),
+                Some(arg) => arg.parse::<usize>().ok().filter(|&n| n <= revisions.len()),
+            };
+            let text = |num: usize| match num {
+                0 => notebook.read_text(&post),
+                n => history::read(&notebook, &revisions[revisions.len() - n]),
+            };
+
+            match args[0].as_str() {
+                "history" => {
+                    let mut lines = vec!["0   current".to_string()];
+                    for (i, revision) in revisions.iter().rev().enumerate() {
+                        lines.push(format!(
+                            "{:<3} {}",
+                            i + 1,
+                            revision.time.format(&config.date_format)
+                        ));
+                    }
+                    page(&config, &lines.join("\n"));
                 }
+                "diff" => {
+                    let (old, new) = match (revision(args.get(3), 1), revision(args.get(4), 0)) {
+                        (Some(old), Some(new)) => (old, new),
+                        _ => return usage(usage_text),
+                    };
+                    let diff = match (text(old), text(new)) {
+                        (Ok(old), Ok(new)) => history::diff(&old, &new),
+                        (Err(e), _) | (_, Err(e)) => return fail(&e),
+                    };
+                    page(&config, &color_diff(&diff));
+                }
+                _ => {
+                    let num = match revision(args.get(3), 0) {
+                        Some(num) if args.len() > 3 && num > 0 => num,
+                        _ => return usage(usage_text),
+                    };
+                    let revision = &revisions[revisions.len() - num];
+                    if let Err(e) = history::restore(&notebook, &post, revision) {
+                        return fail(&e);
+                    }
+                    println!(
+                        "Restored {} from {}",
+                        post.display(),
+                        revision.time.format(&config.date_format)
+                    );
+                }
+            }
+        }
+
+        "stats" => {
+            let json = take_flag(&mut args, "--json");
+            if args.len() < 2 {
+                return usage("jw stats NOTEBOOK [--json]");
+            }
 
-                let results = notebook.search(&args[2]);
-                for path in results {
-                    println!("{}", path.display());
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let stats = match stats::for_notebook(&notebook) {
+                Ok(stats) => stats,
+                Err(e) => return fail(&e),
+            };
+            if json {
+                match stats.to_json() {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => return fail(&e),
                 }
+                return ExitCode::SUCCESS;
+            }
+
+            let today = match notebook.read_config() {
+                Ok(config) => config.now().date_naive(),
+                Err(e) => return fail(&e),
+            };
+            let mut lines = vec![
+                format!("Posts:          {}", stats.posts),
+                format!(
+                    "Words:          {} ({} per post)",
+                    stats.words, stats.average_words
+                ),
+                format!("Current streak: {}", days(stats.current_streak)),
+                format!("Longest streak: {}", days(stats.longest_streak)),
+                String::new(),
+                stats.heatmap(today, 52),
+                String::new(),
+            ];
+            let weekdays = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+            lines.extend(bars(&weekdays, &stats.weekdays));
+            lines.push(String::new());
+            let hours: Vec<String> = (0..24).map(|hour| format!("{:02}h", hour)).collect();
+            lines.extend(bars(&hours, &stats.hours));
+            page(&config, &lines.join("\n"));
+        }
+
+        "calendar" | "onthisday" => {
+            let usage_text = match args[0].as_str() {
+                "calendar" => "jw calendar NOTEBOOK [YEAR-MONTH|YEAR-MONTH-DAY]",
+                _ => "jw onthisday NOTEBOOK",
+            };
+            if args.len() < 2 {
+                return usage(usage_text);
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let notebook_config = match notebook.read_config() {
+                Ok(config) => config,
+                Err(e) => return fail(&e),
+            };
+            let today = notebook_config.now().date_naive();
+            let date_format = notebook_config
+                .date_format
+                .unwrap_or_else(|| config.date_format.clone());
+            let days = match calendar::posts_by_day(&notebook) {
+                Ok(days) => days,
+                Err(e) => return fail(&e),
+            };
+
+            let posts: Vec<&Post> = if args[0] == "onthisday" {
+                calendar::on_this_day(&days, today)
             } else {
-                open_error(&args[1]);
-                return ExitCode::FAILURE;
+                let arg = match args.get(2) {
+                    Some(arg) => arg.clone(),
+                    None => today.format("%Y-%m").to_string(),
+                };
+                if let Ok(date) = NaiveDate::parse_from_str(&arg, "%Y-%m-%d") {
+                    days.get(&date)
+                        .map(|posts| posts.iter().collect())
+                        .unwrap_or_default()
+                } else {
+                    let first = NaiveDate::parse_from_str(&format!("{}-01", arg), "%Y-%m-%d");
+                    match first
+                        .ok()
+                        .and_then(|d| calendar::month(&days, d.year(), d.month()))
+                    {
+                        Some(grid) => println!("{}", grid),
+                        None => return usage(usage_text),
+                    }
+                    return ExitCode::SUCCESS;
+                }
+            };
+
+            if posts.is_empty() {
+                println!("No posts");
             }
+            let lines: Vec<String> = posts
+                .iter()
+                .map(|post| {
+                    let date = post
+                        .timestamp()
+                        .map(|dt| dt.format(&date_format).to_string());
+                    format!(
+                        "{}  {}  ({})",
+                        date.unwrap_or_default(),
+                        post.title(),
+                        post.path.display()
+                    )
+                })
+                .collect();
+            page(&config, &lines.join("\n"));
         }
 
-        "tags" => {
+        "random" => {
+            let tag = take_option(&mut args, "--tag");
             if args.len() < 2 {
-                eprintln!("Usage: jw tags NOTEBOOK");
-                return ExitCode::FAILURE;
+                return usage("jw random NOTEBOOK [--tag TAG]");
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            match review::random(&notebook, tag.as_deref()) {
+                Ok(Some(post)) => page(&config, &show_post(&post, &config.date_format)),
+                Ok(None) => {
+                    eprintln!("No posts found");
+                    return ExitCode::FAILURE;
+                }
+                Err(e) => return fail(&e),
             }
+        }
+
+        "review" => {
+            let new = match take_option(&mut args, "--new").map(|n| n.parse::<usize>()) {
+                None => 5,
+                Some(Ok(new)) => new,
+                Some(Err(_)) => return usage("jw review NOTEBOOK [--new NUM]"),
+            };
+            if args.len() < 2 {
+                return usage("jw review NOTEBOOK [--new NUM]");
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let (posts, mut reviews) =
+                match (notebook.get_posts(), review::Reviews::load(&notebook)) {
+                    (Ok(posts), Ok(reviews)) => (posts, reviews),
+                    (Err(e), _) | (_, Err(e)) => return fail(&e),
+                };
+            let today = match notebook.read_config() {
+                Ok(config) => config.now().date_naive(),
+                Err(e) => return fail(&e),
+            };
 
-            if let Some(notebook) = notebook {
-                let tags = notebook.tags();
-                let mut tags: Vec<(&String, &usize)> = tags.iter().collect();
-                tags.sort();
+            let due = reviews.due(&posts, today, new);
+            if due.is_empty() {
+                println!("Nothing to review today");
+                return ExitCode::SUCCESS;
+            }
+            let mut reviewed = 0;
+            for (i, path) in due.iter().enumerate() {
+                let post = match notebook.read_post(path) {
+                    Ok(post) => post,
+                    Err(e) => return fail(&e),
+                };
+                println!(
+                    "\n({}/{}) {}",
+                    i + 1,
+                    due.len(),
+                    show_post(&post, &config.date_format)
+                );
+
+                let grade = loop {
+                    print!("[a]gain, [h]ard, [g]ood, [e]asy or [q]uit? ");
+                    _ = io::stdout().flush();
+                    let mut answer = String::new();
+                    if io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+                        break None;
+                    }
+                    match answer.trim() {
+                        "a" => break Some(review::Grade::Again),
+                        "h" => break Some(review::Grade::Hard),
+                        "g" | "" => break Some(review::Grade::Good),
+                        "e" => break Some(review::Grade::Easy),
+                        "q" => break None,
+                        _ => continue,
+                    }
+                };
+                let grade = match grade {
+                    Some(grade) => grade,
+                    None => break,
+                };
+                let next = reviews.grade(path, grade, today);
+                println!("Next review on {}", next.format("%Y-%m-%d"));
+                reviewed += 1;
+            }
+
+            if let Err(e) = reviews.save() {
+                return fail(&e);
+            }
+            if let Err(e) = notebook.commit("Review posts") {
+                return fail(&e);
+            }
+            println!("Reviewed {} of {} posts", reviewed, due.len());
+        }
+
+        "tasks" => {
+            let open_only = take_flag(&mut args, "--open");
+            let done = args.len() > 3 && args[1] == "done";
+            let usage_text = "jw tasks NOTEBOOK [--open]\n       jw tasks done NOTEBOOK ID";
+            if args.len() < 2 || (args[1] == "done" && !done) {
+                return usage(usage_text);
+            }
 
-                for (tag, count) in tags {
-                    println!("#{tag}: {count}");
+            let notebook = match open(&args[if done { 2 } else { 1 }]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let tasks = match tasks::list(&notebook) {
+                Ok(tasks) => tasks,
+                Err(e) => return fail(&e),
+            };
+
+            if done {
+                let task = match args[3].parse::<usize>() {
+                    Ok(id) if id > 0 && id <= tasks.len() => &tasks[id - 1],
+                    _ => {
+                        eprintln!("There is no task {}", args[3]);
+                        return usage(usage_text);
+                    }
+                };
+                if task.done {
+                    println!("Already done: {}", task.text);
+                } else if let Err(e) = tasks::complete(&notebook, task) {
+                    return fail(&e);
+                } else {
+                    println!("Done: {}", task.text);
                 }
+                return ExitCode::SUCCESS;
+            }
+
+            let date_format = match notebook.read_config() {
+                Ok(notebook_config) => notebook_config
+                    .date_format
+                    .unwrap_or_else(|| config.date_format.clone()),
+                Err(e) => return fail(&e),
+            };
+            let lines: Vec<String> = tasks
+                .iter()
+                .filter(|task| !(open_only && task.done))
+                .map(|task| {
+                    let date = task.date.map(|dt| dt.format(&date_format).to_string());
+                    format!(
+                        "{:>4} [{}] {}  ({}, {})",
+                        task.id,
+                        if task.done { 'x' } else { ' ' },
+                        task.text,
+                        date.unwrap_or_default(),
+                        task.post.display()
+                    )
+                })
+                .collect();
+            if lines.is_empty() {
+                println!("No tasks");
             } else {
-                open_error(&args[1]);
-                return ExitCode::FAILURE;
+                page(&config, &lines.join("\n"));
             }
         }
 
-        "path" => {
+        "track" => {
+            let csv = take_flag(&mut args, "--csv");
+            let sparkline = take_flag(&mut args, "--sparkline");
+            let since = take_option(&mut args, "--since");
+            let usage_text = "jw track NOTEBOOK [FIELD] [--since DATE|30d] [--csv|--sparkline]";
             if args.len() < 2 {
-                eprintln!("Usage: jw path NOTEBOOK");
-                return ExitCode::FAILURE;
+                return usage(usage_text);
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let notebook_config = match notebook.read_config() {
+                Ok(config) => config,
+                Err(e) => return fail(&e),
+            };
+
+            let field = match args.get(2) {
+                Some(field) => field,
+                None => match fields::names(&notebook) {
+                    Ok(names) if names.is_empty() => {
+                        println!("No fields");
+                        return ExitCode::SUCCESS;
+                    }
+                    Ok(names) => {
+                        for (name, count) in names {
+                            println!("{} ({})", name, count);
+                        }
+                        return ExitCode::SUCCESS;
+                    }
+                    Err(e) => return fail(&e),
+                },
+            };
+            let since = match since {
+                Some(since) => {
+                    match fields::parse_since(&since, notebook_config.now().date_naive()) {
+                        Some(date) => Some(date),
+                        None => return usage(usage_text),
+                    }
+                }
+                None => None,
+            };
+            let points = match fields::series(&notebook, field, since) {
+                Ok(points) => points,
+                Err(e) => return fail(&e),
+            };
+
+            if csv {
+                print!("{}", fields::to_csv(field, &points));
+                return ExitCode::SUCCESS;
+            }
+            if points.is_empty() {
+                println!("No posts with {}", field);
+                return ExitCode::SUCCESS;
+            }
+            if sparkline {
+                println!("{}", fields::sparkline(&points));
+                return ExitCode::SUCCESS;
             }
 
-            if let Some(notebook) = notebook {
-                println!("{}", notebook.path.display());
+            let date_format = notebook_config
+                .date_format
+                .unwrap_or_else(|| config.date_format.clone());
+            let mut lines: Vec<String> = points
+                .iter()
+                .map(|point| format!("{}  {}", point.time.format(&date_format), point.value))
+                .collect();
+            let numbers: Vec<f64> = points
+                .iter()
+                .filter_map(|point| point.value.as_number())
+                .collect();
+            if !numbers.is_empty() {
+                let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let average = numbers.iter().sum::<f64>() / numbers.len() as f64;
+                lines.push(String::new());
+                lines.push(fields::sparkline(&points));
+                lines.push(format!("min {}  average {:.2}  max {}", min, average, max));
             }
+            page(&config, &lines.join("\n"));
         }
 
-        "edit" => {
-            if args.len() < 2 {
-                eprintln!("Usage: jw edit NOTEBOOK [NUM]");
+        "tui" => {
+            if !io::stdout().is_terminal() {
+                eprintln!("Error: jw tui needs a terminal");
                 return ExitCode::FAILURE;
             }
+            if let Err(e) = tui::run(&config) {
+                return fail(&e);
+            }
+        }
 
-            if let Some(notebook) = notebook {
-                let posts = notebook.get_posts().unwrap();
+        "fsck" => {
+            if args.len() < 2 {
+                return usage("jw fsck NOTEBOOK");
+            }
 
-                let num = if args.len() < 3 {
-                    0
-                } else if let Ok(num) = args[2].parse::<usize>() {
-                    num
-                } else {
-                    eprintln!("Error: index must be a number");
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            match fsck::check(&notebook) {
+                Ok(problems) if problems.is_empty() => println!("No problems found"),
+                Ok(problems) => {
+                    for problem in &problems {
+                        println!("{}", problem);
+                    }
                     return ExitCode::FAILURE;
-                };
+                }
+                Err(e) => return fail(&e),
+            }
+        }
 
-                if let Some(path) = posts.iter().rev().nth(num) {
-                    match open_editor(&notebook.path.join(path)) {
-                        Ok(_) => {}
-                        Err(p) => {
-                            eprintln!("Failed to open file: {}", p.display());
+        "export" => {
+            if args.len() < 3 {
+                return usage("jw export FORMAT NOTEBOOK ...");
+            }
+
+            let notebook = match open(&args[2]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+
+            let format = args[1].clone();
+            match format.as_str() {
+                "html" => {
+                    if args.len() < 4 {
+                        return usage("jw export html NOTEBOOK OUT_DIR [TEMPLATES]");
+                    }
+
+                    let out_dir = path::Path::new(&args[3]);
+                    let templates = args.get(4).map(path::Path::new);
+                    if let Err(e) = export::html::export(&notebook, out_dir, templates) {
+                        eprintln!("Error exporting notebook: {}", e);
+                        return exit_code(&e);
+                    }
+                    println!("Exported {} to {}", notebook.id, out_dir.display());
+                }
+                "feed" => {
+                    let rss = take_flag(&mut args, "--rss");
+                    let count = match args.get(3).map(|n| n.parse::<usize>()) {
+                        None => DEFAULT_FEED_SIZE,
+                        Some(Ok(n)) => n,
+                        Some(Err(_)) => return usage("jw export feed NOTEBOOK [COUNT] [--rss]"),
+                    };
+
+                    let feed = if rss {
+                        export::feed::rss(&notebook, count)
+                    } else {
+                        export::feed::atom(&notebook, count)
+                    };
+                    match feed {
+                        Ok(feed) => print!("{}", feed),
+                        Err(e) => {
+                            eprintln!("Error exporting feed: {}", e);
+                            return exit_code(&e);
                         }
                     }
-                } else {
-                    eprintln!("Error: number too large. No such post.");
+                }
+                format @ ("markdown" | "json" | "epub") => {
+                    let mut range = export::DateRange::default();
+                    for (flag, bound) in [("--from", &mut range.from), ("--to", &mut range.to)] {
+                        if let Some(date) = take_option(&mut args, flag) {
+                            match chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+                                Ok(date) => *bound = Some(date),
+                                Err(_) => {
+                                    eprintln!("Error: dates must be formatted as YYYY-MM-DD");
+                                    return ExitCode::from(USAGE);
+                                }
+                            }
+                        }
+                    }
+                    if args.len() < 4 {
+                        return usage(&format!(
+                            "jw export {} NOTEBOOK OUT_FILE [--from DATE] [--to DATE]",
+                            format
+                        ));
+                    }
+
+                    let out = path::Path::new(&args[3]);
+                    let result = match format {
+                        "markdown" => export::markdown::export(&notebook, range)
+                            .and_then(|doc| fs::write(out, doc).context("write", out)),
+                        "json" => export::json::export(&notebook, range)
+                            .and_then(|doc| fs::write(out, doc).context("write", out)),
+                        _ => export::epub::export(&notebook, range, out),
+                    };
+                    if let Err(e) = result {
+                        eprintln!("Error exporting notebook: {}", e);
+                        return exit_code(&e);
+                    }
+                    println!("Exported {} to {}", notebook.id, out.display());
+                }
+                format => {
+                    eprintln!("Error: unknown export format: {}", format);
+                    return ExitCode::from(USAGE);
+                }
+            }
+        }
+
+        "encrypt" => {
+            let key_file = take_option(&mut args, "--key-file");
+            if args.len() < 2 {
+                return usage("jw encrypt NOTEBOOK [--key-file PATH]");
+            }
+
+            let mut notebook = match load(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let passphrase = if key_file.is_none() {
+                match read_passphrase(true) {
+                    Ok(passphrase) => Some(passphrase),
+                    Err(e) => return fail(&e),
                 }
             } else {
-                open_error(&args[1]);
-                return ExitCode::FAILURE;
+                None
+            };
+
+            let key_file = key_file.map(path::PathBuf::from);
+            if let Err(e) = notebook.encrypt(passphrase.as_deref(), key_file.as_deref()) {
+                eprintln!("Error encrypting notebook: {}", e);
+                return exit_code(&e);
+            }
+            println!("Encrypted {}", notebook.id);
+        }
+
+        "decrypt" => {
+            if args.len() < 2 {
+                return usage("jw decrypt NOTEBOOK");
+            }
+
+            let mut notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            if let Err(e) = notebook.decrypt() {
+                eprintln!("Error decrypting notebook: {}", e);
+                return exit_code(&e);
+            }
+            println!("Decrypted {}", notebook.id);
+        }
+
+        "draft" => {
+            if args.len() < 2 {
+                return usage("jw draft NOTEBOOK [list|edit NUM|publish NUM|delete NUM]");
+            }
+
+            let notebook = match open(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            let drafts = match notebook.list_drafts() {
+                Ok(drafts) => drafts,
+                Err(e) => {
+                    eprintln!("Error reading drafts: {}", e);
+                    return exit_code(&e);
+                }
+            };
+
+            match args.get(2).map(|s| s.as_str()) {
+                None => {
+                    let ext = match notebook.read_config() {
+                        Ok(config) => config.extension(),
+                        Err(_) => ".md".to_string(),
+                    };
+                    let scratch = match Scratch::create(&notebook.id, &ext) {
+                        Ok(scratch) => scratch,
+                        Err(e) => {
+                            eprintln!("Error creating temporary file: {}", e);
+                            return exit_code(&e);
+                        }
+                    };
+                    let text = match editor::edit_for(&notebook, &scratch.path) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            _ = scratch.keep(&notebook);
+                            eprintln!("Error: {}", e);
+                            eprintln!(
+                                "Draft not saved. Any text is kept in {}",
+                                scratch.path.display()
+                            );
+                            return exit_code(&e);
+                        }
+                    };
+                    if text.is_empty() {
+                        eprintln!("Draft empty; not saved.");
+                        _ = scratch.discard();
+                        return ExitCode::SUCCESS;
+                    }
+                    match notebook.save_draft(&text) {
+                        Ok(path) => {
+                            println!("Saved draft to {:?}", path);
+                            _ = scratch.discard();
+                        }
+                        Err(e) => {
+                            _ = scratch.keep(&notebook);
+                            eprintln!("Error saving draft: {}", e);
+                            eprintln!("Your text is kept in {}", scratch.path.display());
+                            return exit_code(&e);
+                        }
+                    }
+                }
+                Some("list") => {
+                    for (i, draft) in drafts.iter().enumerate() {
+                        let text = notebook.read_text(draft).unwrap_or_default();
+                        let first_line = text.lines().find(|l| !l.trim().is_empty());
+                        println!("{:<3} {}", i, first_line.unwrap_or_default());
+                    }
+                }
+                Some(action @ ("edit" | "publish" | "delete")) => {
+                    let draft = match args.get(3).and_then(|n| n.parse::<usize>().ok()) {
+                        Some(num) if num < drafts.len() => &drafts[num],
+                        _ => return usage(&format!("jw draft NOTEBOOK {} NUM", action)),
+                    };
+
+                    let result = match action {
+                        "edit" => edit_file(&notebook, draft).and_then(|_| {
+                            notebook.commit(&format!("Edit draft {}", draft.display()))
+                        }),
+                        "publish" => notebook.publish_draft(draft).map(|path| {
+                            println!("Wrote post to {:?}", path);
+                        }),
+                        _ => notebook.delete_draft(draft),
+                    };
+                    if let Err(e) = result {
+                        return fail(&e);
+                    }
+                }
+                Some(_) => {
+                    return usage("jw draft NOTEBOOK [list|edit NUM|publish NUM|delete NUM]");
+                }
+            }
+        }
+
+        "recover" => {
+            let unsaved = match scratch::list(None) {
+                Ok(unsaved) => unsaved,
+                Err(e) => {
+                    eprintln!("Error reading unsaved posts: {}", e);
+                    return exit_code(&e);
+                }
+            };
+
+            match args.get(1).map(|s| s.as_str()) {
+                None | Some("list") => {
+                    for (i, scratch) in unsaved.iter().enumerate() {
+                        // Text meant for an encrypted notebook isn't shown
+                        let text = match Notebook::load(&scratch.notebook) {
+                            Ok(notebook) if notebook.read_config().is_ok_and(|c| c.encrypted) => {
+                                "(encrypted)".to_string()
+                            }
+                            Ok(notebook) => scratch.text(&notebook).unwrap_or_default(),
+                            Err(_) => String::new(),
+                        };
+                        let first_line = text.lines().find(|l| !l.trim().is_empty());
+                        println!(
+                            "{:<3} {:<12} {}  {}",
+                            i,
+                            scratch.notebook,
+                            scratch.created.format(&config.date_format),
+                            first_line.unwrap_or_default()
+                        );
+                    }
+                }
+                Some(action @ ("resume" | "discard")) => {
+                    let scratch = match args.get(2).and_then(|n| n.parse::<usize>().ok()) {
+                        Some(num) if num < unsaved.len() => unsaved[num].clone(),
+                        _ => return usage(&format!("jw recover {} NUM", action)),
+                    };
+
+                    if action == "discard" {
+                        if let Err(e) = scratch.discard() {
+                            eprintln!("Error discarding unsaved post: {}", e);
+                            return exit_code(&e);
+                        }
+                    } else {
+                        return match open(&scratch.notebook) {
+                            Ok(notebook) => post_from_scratch(&notebook, scratch, None, &[]),
+                            Err(code) => code,
+                        };
+                    }
+                }
+                Some(_) => {
+                    return usage("jw recover [list|resume NUM|discard NUM]");
+                }
+            }
+        }
+
+        "sync" => {
+            if args.len() < 2 {
+                return usage("jw sync NOTEBOOK");
+            }
+
+            let notebook = match load(&args[1]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+            if let Err(e) = notebook.sync() {
+                eprintln!("Error syncing notebook: {}", e);
+                return exit_code(&e);
+            }
+        }
+
+        "import" => {
+            if args.len() < 4 {
+                return usage("jw import jrnl|dayone|nb|dir SOURCE NOTEBOOK");
+            }
+
+            let format = match args[1].parse::<import::Format>() {
+                Ok(format) => format,
+                Err(e) => return fail(&e),
+            };
+            let notebook = match open(&args[3]) {
+                Ok(notebook) => notebook,
+                Err(code) => return code,
+            };
+
+            match import::import(&notebook, format, path::Path::new(&args[2])) {
+                Ok(count) => println!("Imported {} posts into {}", count, notebook.id),
+                Err(e) => {
+                    eprintln!("Error importing posts: {}", e);
+                    return exit_code(&e);
+                }
             }
         }
 
         arg => {
             eprintln!("Error: unknown subcommand: {}", arg);
-            return ExitCode::FAILURE;
+            return ExitCode::from(USAGE);
         }
     }
 
     ExitCode::SUCCESS
 }
 
-fn open_error(id: &str) {
-    eprintln!(
-        "Error when opening notebook: {}\nUse jw new NOTEBOOK to create a new one",
-        id
-    );
+/// The exit status for a wrong invocation
+const USAGE: u8 = 2;
+
+fn usage(text: &str) -> ExitCode {
+    eprintln!("Usage: {}", text);
+    ExitCode::from(USAGE)
 }
 
-fn get_text(ext: &str) -> Result<String, path::PathBuf> {
-    let mut path = env::temp_dir();
-    path.push("jw-".to_owned() + &random_string(10) + ext);
-    if let Ok(s) = open_editor(&path) {
-        _ = fs::remove_file(&path);
-        Ok(s)
+/// The exit status for an error, so scripts can tell failures apart.
+fn exit_code(e: &Error) -> ExitCode {
+    ExitCode::from(match e {
+        Error::NotebookNotFound(_) | Error::Exists(_) => 3,
+        Error::Config { .. } | Error::Setting { .. } | Error::Dirs => 4,
+        Error::Locked | Error::Crypto(_) => 5,
+        Error::Editor(_) => 6,
+        Error::InvalidName(_) | Error::InvalidTag(_) => USAGE,
+        _ => 1,
+    })
+}
+
+/// Prints an error, with a hint on how to fix it if there is one.
+fn fail(e: &Error) -> ExitCode {
+    if COLOR.load(Ordering::Relaxed) {
+        eprintln!("\x1b[1;31mError:\x1b[0m {}", e);
     } else {
-        Err(path)
+        eprintln!("Error: {}", e);
+    }
+    match e {
+        Error::NotebookNotFound(_) => eprintln!("Use jw new NOTEBOOK to create a new one"),
+        Error::MissingIndex(_) => {
+            eprintln!("Create an empty posts.txt if the notebook has no posts")
+        }
+        _ => {}
     }
+    exit_code(e)
 }
 
-fn open_editor(path: &path::Path) -> Result<String, path::PathBuf> {
-    let editor = env::var("EDITOR").unwrap_or_else(|_| DEFAULT_EDITOR.into());
+/// Puts the default notebook into the arguments of a subcommand that was
+/// given none. Only subcommands whose arguments can't be mistaken for a
+/// notebook name get one.
+fn add_default_notebook(args: &mut Vec<String>, id: &str) {
+    let first = args.get(1).map(|arg| arg.as_str());
+    let is_notebook = first.is_some_and(|arg| Notebook::load(arg).is_ok());
+    let missing = match args[0].as_str() {
+        "post" | "repl" | "tags" | "path" | "sync" => first.is_none_or(|arg| arg.starts_with("--")),
+        "edit" | "delete" => !is_notebook && first.is_none_or(|arg| arg.parse::<usize>().is_ok()),
+        "draft" => {
+            !is_notebook
+                && first.is_none_or(|arg| {
+                    matches!(arg, "list" | "edit" | "publish" | "delete") || arg.starts_with("--")
+                })
+        }
+        "search" => args.len() == 2,
+        _ => false,
+    };
+    if missing {
+        args.insert(1, id.to_string());
+    }
+}
 
-    let mut split = editor.split_whitespace();
-    let editor = split.next().unwrap();
-    let args: Vec<&str> = split.collect();
+/// Adds the arguments from `[defaults]`, leaving out options that were
+/// given on the command line.
+fn add_default_args(args: &mut Vec<String>, defaults: Vec<String>) {
+    let mut defaults = defaults.into_iter().peekable();
+    while let Some(arg) = defaults.next() {
+        if arg.starts_with("--") && args.contains(&arg) {
+            // Skip the option's value too
+            if defaults.peek().is_some_and(|next| !next.starts_with("--")) {
+                defaults.next();
+            }
+            continue;
+        }
+        args.push(arg);
+    }
+}
 
-    if process::Command::new(editor)
-        .args(&args)
-        .arg(&path)
-        .status()
-        .is_err()
+/// Prints long output through the configured pager when writing to a terminal.
+fn page(config: &config::Config, text: &str) {
+    let pager = match &config.pager {
+        Some(pager) if io::stdout().is_terminal() => shell_words::split(pager).ok(),
+        _ => None,
+    };
+    let child = pager.and_then(|words| {
+        let (program, args) = words.split_first()?;
+        process::Command::new(program)
+            .args(args)
+            .stdin(process::Stdio::piped())
+            .spawn()
+            .ok()
+    });
+
+    match child {
+        Some(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                _ = writeln!(stdin, "{}", text);
+            }
+            _ = child.wait();
+        }
+        None if text.is_empty() => {}
+        // Output may be piped to a program that stops reading early
+        None => _ = writeln!(io::stdout(), "{}", text),
+    }
+}
+
+/// Loads a notebook, printing why it can't be.
+fn load(id: &str) -> Result<Notebook, ExitCode> {
+    Notebook::load(id).map_err(|e| fail(&e))
+}
+
+/// Loads a notebook and unlocks it if it is encrypted.
+fn open(id: &str) -> Result<Notebook, ExitCode> {
+    let mut notebook = load(id)?;
+    unlock(&mut notebook)?;
+    Ok(notebook)
+}
+
+/// Removes `flag` from the arguments, returning whether it was given.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != len
+}
+
+/// Removes `option` and its value from the arguments, returning the value.
+fn take_option(args: &mut Vec<String>, option: &str) -> Option<String> {
+    let i = args.iter().position(|arg| arg == option)?;
+    args.remove(i);
+    if i < args.len() {
+        Some(args.remove(i))
+    } else {
+        None
+    }
+}
+
+/// Unlocks an encrypted notebook, asking for the passphrase if it needs one.
+/// Prints an error and returns the exit status on failure.
+fn unlock(notebook: &mut Notebook) -> Result<(), ExitCode> {
+    let passphrase = match notebook.needs_passphrase() {
+        Ok(true) => Some(read_passphrase(false).map_err(|e| fail(&e))?),
+        Ok(false) => None,
+        Err(e) => return Err(fail(&e)),
+    };
+
+    notebook.unlock(passphrase.as_deref()).map_err(|e| {
+        eprintln!("Error unlocking {}: {}", notebook.id, e);
+        exit_code(&e)
+    })
+}
+
+/// Reads a passphrase from `JW_PASSPHRASE` or the terminal.
+fn read_passphrase(confirm: bool) -> Result<String, Error> {
+    if let Ok(passphrase) = env::var("JW_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+
+    let passphrase = rpassword::prompt_password("Passphrase: ").map_err(Error::IO)?;
+    if confirm
+        && rpassword::prompt_password("Confirm passphrase: ").map_err(Error::IO)? != passphrase
     {
-        eprintln!(
-            "Failed to find editor. Set EDITOR or install {} to resolve.",
-            DEFAULT_EDITOR
-        );
-        return Err(path.to_owned());
+        return Err(Error::Crypto("passphrases don't match".to_string()));
+    }
+    Ok(passphrase)
+}
+
+/// Decrypts a post into a private file in the cache for editing, then encrypts the result.
+fn edit_encrypted(notebook: &Notebook, post: &path::Path) -> Result<(), Error> {
+    let text = notebook.read_text(post)?;
+
+    let ext = match post.extension() {
+        Some(ext) => ".".to_string() + &ext.to_string_lossy(),
+        None => "".to_owned(),
+    };
+    let path = scratch::create_temp(&ext)?;
+    if let Err(e) = fs::write(&path, text) {
+        _ = fs::remove_file(&path);
+        return Err(e).context("write", &path);
+    }
+
+    let edited = editor::edit_for(notebook, &path);
+    _ = fs::remove_file(&path);
+    notebook.write_text(post, &edited?)
+}
+
+/// Opens a post or draft in the editor, decrypting it first if needed.
+fn edit_file(notebook: &Notebook, path: &path::Path) -> Result<(), Error> {
+    if notebook.read_config()?.encrypted {
+        edit_encrypted(notebook, path)
+    } else {
+        editor::edit_for(notebook, &notebook.path.join(path)).map(|_| ())
+    }
+}
+
+/// Opens a scratch file in the editor and posts what was written. The
+/// scratch file is only removed once the post is saved, so it can be recovered.
+/// Nothing is posted if the text is left empty or as the `start` it was given,
+/// and `attach` are only copied into the notebook when it is.
+fn post_from_scratch(
+    notebook: &Notebook,
+    scratch: Scratch,
+    start: Option<&str>,
+    attach: &[String],
+) -> ExitCode {
+    if let Err(e) = scratch.restore(notebook) {
+        return fail(&e);
+    }
+    let text = match editor::edit_for(notebook, &scratch.path) {
+        Ok(text) => text,
+        Err(e) => {
+            _ = scratch.keep(notebook);
+            eprintln!("Error: {}", e);
+            eprintln!(
+                "Post not saved. Any text is kept in {}",
+                scratch.path.display()
+            );
+            eprintln!("Recover it later with jw recover");
+            return exit_code(&e);
+        }
+    };
+
+    if text.is_empty() || start.is_some_and(|t| t.trim() == text.trim()) {
+        eprintln!("Post empty; not saved.");
+        _ = scratch.discard();
+        return ExitCode::SUCCESS;
+    }
+    for file in attach {
+        if let Err(e) = notebook.attach(file) {
+            _ = scratch.keep(notebook);
+            eprintln!("Error attaching {}: {}", file, e);
+            eprintln!("Your text is kept in {}", scratch.path.display());
+            return exit_code(&e);
+        }
+    }
+
+    match notebook.post(&text) {
+        Ok(path) => {
+            println!("Wrote post to {:?}", path);
+            _ = scratch.discard();
+            ExitCode::SUCCESS
+        }
+        // The post is saved, so keeping the text would only lead to a duplicate
+        Err(e @ Error::Uncommitted { .. }) => {
+            eprintln!("Warning: {}", e);
+            _ = scratch.discard();
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            _ = scratch.keep(notebook);
+            eprintln!("Error writing post: {}", e);
+            eprintln!("Your text is kept in {}", scratch.path.display());
+            exit_code(&e)
+        }
     }
+}
+
+fn print_info(notebook: &Notebook, info: &notebooks::Info, config: &config::Config) {
+    let date_format = info
+        .config
+        .date_format
+        .as_ref()
+        .unwrap_or(&config.date_format);
+    let date = |date: Option<chrono::DateTime<chrono::FixedOffset>>| match date {
+        Some(date) => date.format(date_format).to_string(),
+        None => "-".to_string(),
+    };
 
-    if let Ok(s) = fs::read_to_string(&path) {
-        Ok(s)
+    println!("{}", info.config.name);
+    if !info.config.description.is_empty() {
+        println!("{}", info.config.description);
+    }
+    println!();
+    println!("Path:       {}", notebook.path.display());
+    println!("Posts:      {} ({} drafts)", info.posts, info.drafts);
+    println!("Tags:       {}", info.tags);
+    println!("Size:       {}", human_size(info.size));
+    println!("First post: {}", date(info.first));
+    println!("Last post:  {}", date(info.last));
+    println!();
+    for (key, value) in info.config.to_table() {
+        if !matches!(key.as_str(), "name" | "description") {
+            println!("{} = {}", key, value);
+        }
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    for unit in ["KiB", "MiB"] {
+        if size < 1024.0 {
+            return format!("{:.1} {}", size, unit);
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} GiB", size)
+}
+
+/// Finds a post by its number, counting back from the latest, or its path.
+fn find_post(notebook: &Notebook, arg: &str) -> Result<path::PathBuf, ExitCode> {
+    let posts = notebook.get_posts().map_err(|e| fail(&e))?;
+    let post = match arg.parse::<usize>() {
+        Ok(num) => posts.into_iter().rev().nth(num),
+        Err(_) => posts.into_iter().find(|post| post.as_os_str() == arg),
+    };
+    post.ok_or_else(|| {
+        eprintln!("Error: no post {} in {}", arg, notebook.id);
+        ExitCode::FAILURE
+    })
+}
+
+/// Colors the added and removed lines of a diff when colors are enabled.
+fn color_diff(diff: &str) -> String {
+    if !COLOR.load(Ordering::Relaxed) {
+        return diff.to_string();
+    }
+    diff.lines()
+        .map(|line| match line.chars().next() {
+            Some('+') => format!("\x1b[32m{}\x1b[0m", line),
+            Some('-') => format!("\x1b[31m{}\x1b[0m", line),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn days(n: usize) -> String {
+    if n == 1 {
+        "1 day".to_string()
     } else {
-        Err(path.to_owned())
+        format!("{} days", n)
     }
 }
 
-fn random_string(len: usize) -> String {
-    (0..len).map(|_| fastrand::alphabetic()).collect()
+/// Draws a bar chart with a line for each label, scaled to the largest count.
+fn bars<S: std::fmt::Display>(labels: &[S], counts: &[usize]) -> Vec<String> {
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+    labels
+        .iter()
+        .zip(counts)
+        .map(|(label, &count)| {
+            let bar = "█".repeat((count * 30).div_ceil(most));
+            format!("{} {:<30} {}", label, bar, count)
+        })
+        .collect()
+}
+
+/// Formats a post for reading in the terminal, with its date and path above it.
+fn show_post(post: &Post, date_format: &str) -> String {
+    let date = post
+        .timestamp()
+        .map(|dt| dt.format(date_format).to_string())
+        .unwrap_or_default();
+    format!(
+        "{}  ({})\n\n{}",
+        date,
+        post.path.display(),
+        post.body.trim()
+    )
+}
+
+/// Asks a yes or no question. Anything but a yes, including the end of the
+/// input, is a no.
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    _ = io::Write::flush(&mut io::stdout());
+
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => false,
+        Ok(_) => answer.trim().to_lowercase().starts_with('y'),
+    }
 }
@@ -0,0 +1,111 @@
+//! `jw repl`, for writing a post per line
+//!
+//! Lines can be edited like in a shell, earlier lines come back with the up
+//! arrow, and tab completes the tags already used in the notebook.
+
+use std::io::{self, IsTerminal};
+
+use jw_internals::{config, quick, search, Error, Notebook};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, Helper,
+};
+
+/// Completes hashtags from the tags of the notebook
+struct Tags(Vec<String>);
+
+impl Completer for Tags {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let prefix = match line[start..pos].strip_prefix('#') {
+            Some(prefix) => prefix.to_lowercase(),
+            None => return Ok((pos, Vec::new())),
+        };
+        let candidates = self
+            .0
+            .iter()
+            .filter(|tag| tag.starts_with(&prefix))
+            .map(|tag| format!("#{tag}"))
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Tags {
+    type Hint = String;
+}
+
+impl Highlighter for Tags {}
+
+impl Validator for Tags {}
+
+impl Helper for Tags {}
+
+fn readline_error(e: ReadlineError) -> Error {
+    match e {
+        ReadlineError::Io(e) => Error::IO(e),
+        e => Error::IO(io::Error::other(e)),
+    }
+}
+
+/// Posts each line that is typed until the input ends.
+pub fn run(notebook: &Notebook) -> Result<(), Error> {
+    let mut tags: Vec<String> = notebook.tags()?.into_keys().collect();
+    tags.sort();
+    let mut editor: Editor<Tags, DefaultHistory> = Editor::new().map_err(readline_error)?;
+    editor.set_helper(Some(Tags(tags)));
+
+    // Lines written to an encrypted notebook aren't kept in plain text
+    let history = if notebook.read_config()?.encrypted {
+        None
+    } else {
+        Some(config::get_cache_path(Some("repl_history"))?)
+    };
+    if let Some(history) = &history {
+        // There is no history the first time
+        _ = editor.load_history(history);
+    }
+
+    if io::stdin().is_terminal() {
+        println!("Each line is a post. Tab completes tags, Ctrl-D quits.");
+    }
+    let prompt = format!("{}> ", notebook.id);
+    loop {
+        let line = match editor.readline(&prompt) {
+            Ok(line) if line.trim().is_empty() => continue,
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => return Err(readline_error(e)),
+        };
+        _ = editor.add_history_entry(line.as_str());
+
+        // Keep going after an error, so the rest of the session isn't lost
+        match quick::capture(notebook, &line) {
+            Ok(_) => {}
+            Err(e @ Error::Uncommitted { .. }) => eprintln!("Warning: {}", e),
+            Err(e) => {
+                _ = crate::fail(&e);
+                continue;
+            }
+        }
+        if let Some(Tags(tags)) = editor.helper_mut() {
+            for tag in search::extract_tags(&line) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+    }
+
+    if let Some(history) = &history {
+        editor.save_history(history).map_err(readline_error)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,522 @@
+//! `jw tui`, for browsing and writing posts without leaving the terminal
+//!
+//! The screen has a list of notebooks, the posts of the selected notebook
+//! (newest first) and a preview of the selected post. Posts are written and
+//! edited in the same editor as `jw post` and `jw edit`, with the interface
+//! put away until the editor exits.
+
+use std::{env, io, path::Path};
+
+use jw_internals::{
+    config, editor, history, notebooks, scratch::Scratch, search, templates, Error, Notebook, Post,
+};
+use ratatui::{
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        execute,
+        terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+
+const HELP: &str =
+    "tab switch  j/k move  J/K scroll  / search  n new  e edit  d delete  t tag  r reload  q quit";
+
+#[derive(PartialEq, Eq)]
+enum Focus {
+    Notebooks,
+    Posts,
+}
+
+/// What typed keys are for
+enum Mode {
+    Normal,
+    Search,
+    Tag(String),
+    Passphrase(String),
+    Delete,
+}
+
+/// What the main loop should do after a key press
+enum Action {
+    Nothing,
+    Quit,
+    New,
+    Edit,
+}
+
+struct App {
+    notebooks: Vec<String>,
+    sidebar: ListState,
+    /// The selected notebook, which is still locked while asking for its passphrase
+    notebook: Option<Notebook>,
+    /// The posts of the selected notebook, newest first
+    posts: Vec<Post>,
+    /// The indexes of the posts matching `query`
+    matches: Vec<usize>,
+    list: ListState,
+    query: String,
+    focus: Focus,
+    mode: Mode,
+    /// The result of the last action, shown instead of the help line
+    message: String,
+    scroll: u16,
+    default_date_format: String,
+    date_format: String,
+}
+
+/// Runs the interface until it is quit.
+pub fn run(config: &config::Config) -> Result<(), Error> {
+    let mut app = App {
+        notebooks: Vec::new(),
+        sidebar: ListState::default(),
+        notebook: None,
+        posts: Vec::new(),
+        matches: Vec::new(),
+        list: ListState::default(),
+        query: String::new(),
+        focus: Focus::Posts,
+        mode: Mode::Normal,
+        message: String::new(),
+        scroll: 0,
+        default_date_format: config.date_format.clone(),
+        date_format: config.date_format.clone(),
+    };
+    app.read_notebooks()?;
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+/// Puts the interface away while `f` runs, such as to open an editor.
+fn suspend<T>(terminal: &mut DefaultTerminal, f: impl FnOnce() -> T) -> io::Result<T> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    let result = f();
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    terminal.clear()?;
+    Ok(result)
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+        loop {
+            terminal.draw(|frame| self.draw(frame)).map_err(Error::IO)?;
+            let key = match event::read().map_err(Error::IO)? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            self.message.clear();
+            match self.handle_key(key) {
+                Action::Nothing => {}
+                Action::Quit => return Ok(()),
+                Action::New => {
+                    self.message = match suspend(terminal, || self.new_post()).map_err(Error::IO)? {
+                        Ok(true) => "Post saved".to_string(),
+                        Ok(false) => "Post empty; not saved".to_string(),
+                        Err(e @ Error::Uncommitted { .. }) => format!("Warning: {}", e),
+                        Err(e) => format!("Error: {}. Recover any text with jw recover", e),
+                    };
+                    self.read_posts();
+                }
+                Action::Edit => {
+                    if let Err(e) = suspend(terminal, || self.edit_post()).map_err(Error::IO)? {
+                        self.message = format!("Error: {}", e);
+                    }
+                    self.read_posts();
+                }
+            }
+        }
+    }
+
+    fn read_notebooks(&mut self) -> Result<(), Error> {
+        let selected = self.sidebar.selected().and_then(|i| self.notebooks.get(i));
+        let selected = selected.cloned();
+        self.notebooks = notebooks::list()?.into_iter().map(|nb| nb.id).collect();
+        let i = selected
+            .and_then(|id| self.notebooks.iter().position(|other| *other == id))
+            .unwrap_or(0);
+        self.sidebar
+            .select(Some(i).filter(|_| !self.notebooks.is_empty()));
+        self.open_notebook();
+        Ok(())
+    }
+
+    /// Loads the notebook selected in the sidebar, asking for its passphrase
+    /// unless it is in `JW_PASSPHRASE`.
+    fn open_notebook(&mut self) {
+        self.notebook = None;
+        self.posts.clear();
+        self.filter();
+        let id = match self.sidebar.selected().and_then(|i| self.notebooks.get(i)) {
+            Some(id) => id,
+            None => return,
+        };
+
+        let notebook = match Notebook::load(id) {
+            Ok(notebook) => notebook,
+            Err(e) => return self.message = format!("Error: {}", e),
+        };
+        self.date_format = match notebook.read_config() {
+            Ok(config) => config
+                .date_format
+                .unwrap_or_else(|| self.default_date_format.clone()),
+            Err(e) => return self.message = format!("Error: {}", e),
+        };
+        let needs_passphrase = notebook.needs_passphrase().unwrap_or(false);
+        self.notebook = Some(notebook);
+        match env::var("JW_PASSPHRASE") {
+            Ok(passphrase) if needs_passphrase => self.unlock(Some(&passphrase)),
+            _ if needs_passphrase => self.mode = Mode::Passphrase(String::new()),
+            _ => self.unlock(None),
+        }
+    }
+
+    fn unlock(&mut self, passphrase: Option<&str>) {
+        if let Some(notebook) = &mut self.notebook {
+            match notebook.unlock(passphrase) {
+                Ok(()) => self.read_posts(),
+                Err(e) => self.message = format!("Error unlocking {}: {}", notebook.id, e),
+            }
+        }
+    }
+
+    fn read_posts(&mut self) {
+        if let Some(notebook) = &self.notebook {
+            match notebook.read_posts() {
+                Ok(posts) => self.posts = posts.into_iter().rev().collect(),
+                Err(e) => self.message = format!("Error: {}", e),
+            }
+        }
+        self.filter();
+    }
+
+    /// Updates the posts matching the search, keeping the selection in place.
+    fn filter(&mut self) {
+        self.matches = (0..self.posts.len())
+            .filter(|&i| {
+                let post = &self.posts[i];
+                search::matches(&post.body, &self.query)
+                    || search::matches(&post.title(), &self.query)
+            })
+            .collect();
+        let selected = match self.list.selected() {
+            _ if self.matches.is_empty() => None,
+            Some(i) => Some(i.min(self.matches.len() - 1)),
+            None => Some(0),
+        };
+        self.list.select(selected);
+        self.scroll = 0;
+    }
+
+    fn selected_post(&self) -> Option<&Post> {
+        let i = self.matches.get(self.list.selected()?)?;
+        self.posts.get(*i)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        match &mut self.mode {
+            Mode::Normal => return self.handle_normal_key(key),
+            Mode::Search => match key.code {
+                KeyCode::Char(c) => self.query.push(c),
+                KeyCode::Backspace => _ = self.query.pop(),
+                KeyCode::Enter => self.mode = Mode::Normal,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.mode = Mode::Normal;
+                }
+                _ => return Action::Nothing,
+            },
+            Mode::Tag(input) | Mode::Passphrase(input) => match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => _ = input.pop(),
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Enter => {
+                    let input = std::mem::take(input);
+                    match std::mem::replace(&mut self.mode, Mode::Normal) {
+                        Mode::Tag(_) => self.tag_post(&input),
+                        _ => self.unlock(Some(&input)),
+                    }
+                }
+                _ => {}
+            },
+            Mode::Delete => {
+                self.mode = Mode::Normal;
+                if key.code == KeyCode::Char('y') {
+                    self.delete_post();
+                }
+            }
+        }
+        if matches!(self.mode, Mode::Normal | Mode::Search) {
+            self.filter();
+        }
+        Action::Nothing
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc => {
+                self.query.clear();
+                self.filter();
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Notebooks => Focus::Posts,
+                    Focus::Posts => Focus::Notebooks,
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Notebooks,
+            KeyCode::Right | KeyCode::Char('l') => self.focus = Focus::Posts,
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Home | KeyCode::Char('g') => self.move_by(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_by(isize::MAX),
+            KeyCode::Char('J') => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Char('K') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::Char('/') => {
+                self.focus = Focus::Posts;
+                self.mode = Mode::Search;
+            }
+            KeyCode::Char('r') => {
+                if let Err(e) = self.read_notebooks() {
+                    self.message = format!("Error: {}", e);
+                }
+            }
+            KeyCode::Char('n') if self.notebook.is_some() => return Action::New,
+            KeyCode::Char('e') if self.selected_post().is_some() => return Action::Edit,
+            KeyCode::Char('d') if self.selected_post().is_some() => self.mode = Mode::Delete,
+            KeyCode::Char('t') if self.selected_post().is_some() => {
+                self.mode = Mode::Tag(String::new())
+            }
+            _ => {}
+        }
+        Action::Nothing
+    }
+
+    /// Moves the selection of the focused list, stopping at either end.
+    fn move_by(&mut self, by: isize) {
+        let (state, len) = match self.focus {
+            Focus::Notebooks => (&mut self.sidebar, self.notebooks.len()),
+            Focus::Posts => (&mut self.list, self.matches.len()),
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        let next = current.saturating_add(by).clamp(0, len as isize - 1) as usize;
+        if state.selected() == Some(next) {
+            return;
+        }
+        state.select(Some(next));
+        match self.focus {
+            Focus::Notebooks => {
+                self.list.select(None);
+                self.open_notebook();
+            }
+            Focus::Posts => self.scroll = 0,
+        }
+    }
+
+    /// Writes a new post in the editor, like `jw post`. Returns whether it was saved.
+    fn new_post(&self) -> Result<bool, Error> {
+        let notebook = match &self.notebook {
+            Some(notebook) => notebook,
+            None => return Ok(false),
+        };
+        let template = templates::for_post(notebook, None)?;
+        let scratch = Scratch::create(&notebook.id, &notebook.read_config()?.extension())?;
+        if let Some(template) = &template {
+            scratch.write(template)?;
+        }
+
+        // Text that can't be posted is kept for `jw recover`
+        let text = match editor::edit_for(notebook, &scratch.path) {
+            Ok(text) => text,
+            Err(e) => {
+                _ = scratch.keep(notebook);
+                return Err(e);
+            }
+        };
+        if text.is_empty() || template.is_some_and(|t| t.trim() == text.trim()) {
+            scratch.discard()?;
+            return Ok(false);
+        }
+        match notebook.post(&text) {
+            Ok(_) => {}
+            Err(e @ Error::Uncommitted { .. }) => {
+                scratch.discard()?;
+                return Err(e);
+            }
+            Err(e) => {
+                _ = scratch.keep(notebook);
+                return Err(e);
+            }
+        }
+        scratch.discard()?;
+        Ok(true)
+    }
+
+    /// Edits the selected post, like `jw edit`.
+    fn edit_post(&self) -> Result<(), Error> {
+        let (notebook, post) = match (&self.notebook, self.selected_post()) {
+            (Some(notebook), Some(post)) => (notebook, &post.path),
+            _ => return Ok(()),
+        };
+        history::snapshot(notebook, post)?;
+        crate::edit_file(notebook, post)?;
+        notebook.commit(&format!("Edit post {}", post.display()))
+    }
+
+    fn delete_post(&mut self) {
+        let (notebook, post) = match (&self.notebook, self.selected_post()) {
+            (Some(notebook), Some(post)) => (notebook, post.path.clone()),
+            _ => return,
+        };
+        self.message = match notebook.delete_post(&post) {
+            Ok(_) => format!("Moved {} to the trash. Undo with jw undo", post.display()),
+            Err(e) => format!("Error: {}", e),
+        };
+        self.read_posts();
+    }
+
+    fn tag_post(&mut self, tag: &str) {
+        let (notebook, post) = match (&self.notebook, self.selected_post()) {
+            (Some(notebook), Some(post)) => (notebook, post.path.clone()),
+            _ => return,
+        };
+        self.message = match notebook.tag_post(&post, tag) {
+            Ok(true) => format!("Tagged {} with #{}", post.display(), tag),
+            Ok(false) => format!("{} already has #{}", post.display(), tag),
+            Err(e) => format!("Error: {}", e),
+        };
+        self.read_posts();
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [sidebar, list, preview] = Layout::horizontal([
+            Constraint::Length(20),
+            Constraint::Percentage(40),
+            Constraint::Min(0),
+        ])
+        .areas(main);
+
+        let block = |title: String, focused: bool| {
+            let block = Block::bordered().title(title);
+            if focused {
+                block.border_style(Style::new().fg(Color::Blue))
+            } else {
+                block
+            }
+        };
+        let highlight = Style::new().add_modifier(Modifier::REVERSED);
+
+        let items: Vec<ListItem> = self
+            .notebooks
+            .iter()
+            .map(|id| ListItem::new(id.as_str()))
+            .collect();
+        let notebooks = List::new(items)
+            .block(block(
+                "Notebooks".to_string(),
+                self.focus == Focus::Notebooks,
+            ))
+            .highlight_style(highlight);
+        frame.render_stateful_widget(notebooks, sidebar, &mut self.sidebar);
+
+        let items: Vec<ListItem> = self
+            .matches
+            .iter()
+            .map(|&i| {
+                let post = &self.posts[i];
+                let date = post
+                    .timestamp()
+                    .map(|dt| dt.format(&self.date_format).to_string())
+                    .unwrap_or_default();
+                let tags: Vec<String> = post.tags().iter().map(|tag| format!("#{tag}")).collect();
+                ListItem::new(Line::from(vec![
+                    Span::raw(date).dim(),
+                    Span::raw("  "),
+                    Span::raw(post.title()),
+                    Span::raw("  "),
+                    Span::raw(tags.join(" ")).fg(Color::Cyan),
+                ]))
+            })
+            .collect();
+        let title = if self.query.is_empty() {
+            format!("Posts ({})", self.matches.len())
+        } else {
+            format!("Posts matching {:?} ({})", self.query, self.matches.len())
+        };
+        let posts = List::new(items)
+            .block(block(title, self.focus == Focus::Posts))
+            .highlight_style(highlight);
+        frame.render_stateful_widget(posts, list, &mut self.list);
+
+        let text = match self.selected_post() {
+            Some(post) => {
+                let date = post
+                    .timestamp()
+                    .map(|dt| dt.format(&self.date_format).to_string())
+                    .unwrap_or_default();
+                let mut text = Text::from(vec![
+                    Line::from(post.title()).bold(),
+                    Line::from(format!("{}  {}", date, post.path.display())).dim(),
+                    Line::default(),
+                ]);
+                text.extend(Text::raw(post.body.trim()));
+                text
+            }
+            None if self.notebooks.is_empty() => {
+                Text::raw("No notebooks yet. Create one with jw new NOTEBOOK")
+            }
+            None if self.notebook.is_some() && self.posts.is_empty() => {
+                Text::raw("No posts yet. Press n to write one")
+            }
+            None => Text::default(),
+        };
+        let preview_widget = Paragraph::new(text)
+            .block(Block::bordered().title("Preview"))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(preview_widget, preview);
+
+        let line = match &self.mode {
+            Mode::Normal if self.message.is_empty() => Line::from(HELP).dim(),
+            Mode::Normal => Line::from(self.message.as_str()),
+            Mode::Search => Line::from(format!("/{}", self.query)),
+            Mode::Tag(tag) => Line::from(format!("Tag with #{}", tag)),
+            Mode::Passphrase(passphrase) => {
+                let id = self.notebook.as_ref().map(|nb| nb.id.as_str());
+                Line::from(format!(
+                    "Passphrase for {}: {}",
+                    id.unwrap_or_default(),
+                    "*".repeat(passphrase.chars().count())
+                ))
+            }
+            Mode::Delete => {
+                let post = self.selected_post().map(|post| post.path.as_path());
+                Line::from(format!(
+                    "Move {} to the trash? [y/N]",
+                    post.unwrap_or(Path::new("")).display()
+                ))
+            }
+        };
+        frame.render_widget(line, status);
+    }
+}
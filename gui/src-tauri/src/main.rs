@@ -3,9 +3,9 @@
     windows_subsystem = "windows"
 )]
 
-use std::{fs, io::Write, process};
+use std::{fs, process};
 
-use jw_internals::{config, notebooks, Notebook};
+use jw_internals::{config, notebooks, stats, Notebook};
 
 use serde_derive::{Deserialize, Serialize};
 
@@ -14,8 +14,17 @@ mod utils;
 #[tauri::command]
 fn save_post(notebook: &str, contents: &str) -> bool {
     println!("Sending: {}", &contents);
-    let notebook = Notebook::load(notebook).unwrap();
+    let notebook = match Notebook::load(notebook) {
+        Ok(notebook) => notebook,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return false;
+        }
+    };
     if notebook.post(contents).is_ok() {
+        let mut state = read_saved_state();
+        clear_draft(&mut state);
+        write_saved_state(&state);
         true
     } else {
         false
@@ -35,7 +44,7 @@ fn notebook_list() -> Vec<String> {
 
 #[tauri::command]
 fn open_folder(id: &str) -> bool {
-    if let Some(notebook) = Notebook::load(id) {
+    if let Ok(notebook) = Notebook::load(id) {
         process::Command::new(utils::file_manager())
             .arg(notebook.path)
             .spawn()
@@ -52,24 +61,61 @@ struct State {
     notebook: Option<String>,
 }
 
-#[tauri::command]
-fn save_state(text: &str, notebook: &str) -> bool {
+/// What is kept in `gui.toml`: the text being written is saved as a draft in
+/// its notebook, so it is never lost and can be finished with `jw draft`.
+#[derive(Default, Serialize, Deserialize)]
+struct SavedState {
+    notebook: Option<String>,
+    draft: Option<String>,
+}
+
+fn read_saved_state() -> SavedState {
     let path = config::get_path(Some("gui.toml")).unwrap();
+    let conf_str = fs::read_to_string(path).unwrap_or("".to_string());
+    toml::from_str(&conf_str).unwrap_or_default()
+}
 
-    let state = State {
-        text: Some(text.to_owned()),
-        notebook: Some(notebook.to_owned()),
-    };
-    let conf_str = toml::to_string(&state).unwrap();
+fn write_saved_state(state: &SavedState) {
+    let path = config::get_path(Some("gui.toml")).unwrap();
+    let conf_str = toml::to_string(state).unwrap();
+    fs::write(path, conf_str).unwrap();
+}
 
-    let mut f = fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(path)
-        .unwrap();
+/// Removes the draft being written, once it is posted or cleared.
+fn clear_draft(state: &mut SavedState) {
+    if let (Some(id), Some(draft)) = (&state.notebook, state.draft.take()) {
+        if let Ok(notebook) = Notebook::load(id) {
+            _ = notebook.delete_draft(draft);
+        }
+    }
+}
 
-    f.write_all(conf_str.as_bytes()).unwrap();
+#[tauri::command]
+fn save_state(text: &str, notebook: &str) -> bool {
+    let mut state = read_saved_state();
+    if state.notebook.as_deref() != Some(notebook) || text.trim().is_empty() {
+        clear_draft(&mut state);
+    }
+    state.notebook = Some(notebook.to_owned());
+
+    if !text.trim().is_empty() {
+        let notebook = match Notebook::load(notebook) {
+            Ok(notebook) => notebook,
+            Err(_) => return false,
+        };
+        let updated = match &state.draft {
+            Some(draft) => notebook.update_draft(draft, text).is_ok(),
+            None => false,
+        };
+        if !updated {
+            match notebook.save_draft(text) {
+                Ok(path) => state.draft = Some(path.to_string_lossy().into_owned()),
+                Err(_) => return false,
+            }
+        }
+    }
 
+    write_saved_state(&state);
     println!("Saved state!");
 
     true
@@ -77,11 +123,22 @@ fn save_state(text: &str, notebook: &str) -> bool {
 
 #[tauri::command]
 fn load_state() -> State {
-    let path = config::get_path(Some("gui.toml")).unwrap();
-    let conf_str = fs::read_to_string(path).unwrap_or("".to_string());
-    let config = toml::from_str(&conf_str).unwrap();
+    let state = read_saved_state();
+    let text = match (&state.notebook, &state.draft) {
+        (Some(id), Some(draft)) => Notebook::load(id).and_then(|n| n.read_text(draft)).ok(),
+        _ => None,
+    };
+
+    State {
+        text,
+        notebook: state.notebook,
+    }
+}
 
-    config
+#[tauri::command]
+fn notebook_stats(id: &str) -> Option<stats::Stats> {
+    let notebook = Notebook::load(id).ok()?;
+    stats::for_notebook(&notebook).ok()
 }
 
 #[tauri::command]
@@ -98,6 +155,7 @@ fn main() {
             open_folder,
             load_state,
             save_state,
+            notebook_stats,
             print_cli,
         ])
         .run(tauri::generate_context!())
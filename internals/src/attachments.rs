@@ -0,0 +1,152 @@
+//! Files and images attached to posts
+//!
+//! Attachments are copied into the notebook's `attachments/` directory and
+//! named after a hash of their contents, so a file attached twice is only
+//! stored once. `attachments.txt` lists each one with the name of the file
+//! it was copied from, separated by a tab:
+//! ```text
+//! 3f1a9c0b5d2e8f47.png    screenshot.png
+//! ```
+//! Posts link to them like any other file, such as
+//! `![screenshot.png](attachments/3f1a9c0b5d2e8f47.png)` in Markdown.
+
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{formats::Format, Context, Error, Notebook, Post};
+
+/// The directory of attachments in a notebook
+pub const DIR: &str = "attachments";
+const INDEX: &str = "attachments.txt";
+const IMAGES: [&str; 7] = ["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// The name of the file in `attachments/`
+    pub name: String,
+    /// The name of the file it was copied from
+    pub original: String,
+}
+
+impl Attachment {
+    /// Names a file as it would be attached, without copying it yet.
+    pub fn for_file(file: &Path) -> Result<Self, Error> {
+        let contents = fs::read(file).context("read", file)?;
+        Ok(Self {
+            name: stored_name(&contents, file),
+            original: file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The path of the attachment, relative to the notebook
+    pub fn path(&self) -> PathBuf {
+        Path::new(DIR).join(&self.name)
+    }
+
+    /// A link to the attachment in a post written in `format`, showing
+    /// images inline.
+    pub fn link(&self, format: Format) -> String {
+        let ext = self.name.rsplit_once('.').map(|(_, ext)| ext);
+        let image = ext.is_some_and(|ext| IMAGES.contains(&ext));
+        let target = format!("{DIR}/{}", self.name);
+        format.handler().link(&target, &self.original, image)
+    }
+}
+
+/// Names a file after its contents, keeping its extension.
+pub(crate) fn stored_name(contents: &[u8], file: &Path) -> String {
+    let hash = format!("{:x}", Sha256::digest(contents));
+    match file.extension() {
+        Some(ext) => format!("{}.{}", &hash[..16], ext.to_string_lossy().to_lowercase()),
+        None => hash[..16].to_string(),
+    }
+}
+
+/// Adds an attachment to `attachments.txt` if it isn't listed yet.
+pub(crate) fn add_to_index(notebook: &Notebook, attachment: &Attachment) -> Result<(), Error> {
+    if list(notebook)?.iter().any(|a| a.name == attachment.name) {
+        return Ok(());
+    }
+    let index = notebook.path.join(INDEX);
+    let mut f = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&index)
+        .context("open", &index)?;
+    writeln!(f, "{}\t{}", attachment.name, attachment.original).context("write", &index)
+}
+
+/// Returns the attachments listed in `attachments.txt`.
+pub fn list(notebook: &Notebook) -> Result<Vec<Attachment>, Error> {
+    let index = notebook.path.join(INDEX);
+    let text = match fs::read_to_string(&index) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("read", index),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let (name, original) = line.split_once('\t')?;
+            Some(Attachment {
+                name: name.to_string(),
+                original: original.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Returns the names of the files in `attachments/`.
+pub fn files(notebook: &Notebook) -> Result<Vec<String>, Error> {
+    let dir = notebook.path.join(DIR);
+    match fs::read_dir(&dir) {
+        Ok(entries) => Ok(entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).context("read", dir),
+    }
+}
+
+/// Returns the names of the attachments linked from a post.
+pub fn links(post: &Post) -> Vec<String> {
+    let prefix = format!("{DIR}/");
+    post.format
+        .handler()
+        .links(&post.body)
+        .into_iter()
+        .filter_map(|target| target.strip_prefix(&prefix).map(str::to_string))
+        .collect()
+}
+
+#[test]
+fn links_to_attachments() {
+    let image = Attachment {
+        name: "3f1a9c0b5d2e8f47.png".to_string(),
+        original: "screenshot.png".to_string(),
+    };
+    assert_eq!(
+        image.link(Format::Markdown),
+        "![screenshot.png](attachments/3f1a9c0b5d2e8f47.png)"
+    );
+    let text = format!(
+        "Look:\n{}\nand [notes](attachments/0123.pdf) or [web](https://example.com)",
+        image.link(Format::Markdown)
+    );
+    let post = Post::parse("2022-05-11-look.md", &text);
+    assert_eq!(links(&post), vec!["3f1a9c0b5d2e8f47.png", "0123.pdf"]);
+
+    let name = stored_name(b"hello", Path::new("a/Notes.PDF"));
+    assert_eq!(name, "2cf24dba5fb0a30e.pdf");
+    assert_eq!(name, stored_name(b"hello", Path::new("other.pdf")));
+}
@@ -0,0 +1,87 @@
+//! Posts arranged by the day they were written
+//!
+//! Days come from the timestamp in a post's front matter, or the date at the
+//! start of its filename. Posts with neither are left out.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::{Error, Notebook, Post};
+
+pub type Days = BTreeMap<NaiveDate, Vec<Post>>;
+
+/// Groups the posts of a notebook by day, each day's posts in the order they
+/// were written.
+pub fn posts_by_day(notebook: &Notebook) -> Result<Days, Error> {
+    let mut days = Days::new();
+    let mut posts: Vec<_> = notebook
+        .read_posts()?
+        .into_iter()
+        .filter_map(|post| Some((post.timestamp()?, post)))
+        .collect();
+    posts.sort_by_key(|(dt, _)| *dt);
+    for (dt, post) in posts {
+        days.entry(dt.date_naive()).or_default().push(post);
+    }
+    Ok(days)
+}
+
+/// Renders a month like `cal`, with a `*` after each day that has posts.
+/// Returns `None` if there is no such month.
+pub fn month(days: &Days, year: i32, month: u32) -> Option<String> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let mut lines = vec![
+        format!("{:^28}", first.format("%B %Y").to_string())
+            .trim_end()
+            .to_string(),
+        " Mo  Tu  We  Th  Fr  Sa  Su".to_string(),
+    ];
+
+    let mut line = "    ".repeat(first.weekday().num_days_from_monday() as usize);
+    let mut day = first;
+    while day.month() == month {
+        let mark = if days.contains_key(&day) { '*' } else { ' ' };
+        line.push_str(&format!(" {:>2}{}", day.day(), mark));
+        if day.weekday().num_days_from_monday() == 6 {
+            lines.push(line.trim_end().to_string());
+            line = String::new();
+        }
+        day += Duration::days(1);
+    }
+    if !line.is_empty() {
+        lines.push(line.trim_end().to_string());
+    }
+    Some(lines.join("\n"))
+}
+
+/// Returns the posts written on the same day of the year as `today` in
+/// earlier years, oldest first.
+pub fn on_this_day(days: &Days, today: NaiveDate) -> Vec<&Post> {
+    days.iter()
+        .filter(|(day, _)| {
+            day.month() == today.month() && day.day() == today.day() && day.year() < today.year()
+        })
+        .flat_map(|(_, posts)| posts)
+        .collect()
+}
+
+#[test]
+fn month_grid() {
+    let mut days = Days::new();
+    let post = Post::parse("2022-05-04-hello.md", "Hello");
+    days.insert(NaiveDate::from_ymd_opt(2022, 5, 4).unwrap(), vec![post]);
+
+    let grid = month(&days, 2022, 5).unwrap();
+    let lines: Vec<&str> = grid.lines().collect();
+    assert_eq!(lines[0].trim(), "May 2022");
+    // May 2022 starts on a Sunday
+    assert_eq!(lines[2], format!("{}  1", " ".repeat(24)));
+    assert_eq!(lines[3], "  2   3   4*  5   6   7   8");
+    assert_eq!(lines.len(), 8);
+    assert!(month(&days, 2022, 13).is_none());
+
+    let today = NaiveDate::from_ymd_opt(2023, 5, 4).unwrap();
+    assert_eq!(on_this_day(&days, today).len(), 1);
+    assert!(on_this_day(&days, today.with_year(2022).unwrap()).is_empty());
+}
@@ -1,45 +1,243 @@
 //! Configuration handlers for Just Write
 //!
 //! The main configuration file is stored in the appropriate platform's
-//! `config_dir`, as provided by `directories-rs`.
+//! `config_dir`, as provided by `directories-rs`. Every setting is optional.
 //!
 //! Example `config.toml`:
 //! ```toml
 //! root = "~/jw/"
+//! editor = "code --wait"
+//! default_notebook = "journal"
+//! pager = "less -R"
+//! color = "auto" # or "always" or "never"
+//! date_format = "%a %d %b %Y, %H:%M" # month and weekday names are in English
+//!
+//! [aliases]
+//! j = "journal"
+//!
+//! [defaults]
+//! post = "--template daily"
+//!
+//! # Defaults for the settings in every notebook.toml
+//! [notebooks]
+//! timezone = "Europe/Berlin"
 //! ```
 
 use std::{
+    collections::BTreeMap,
     fs,
     path::{Path, PathBuf},
 };
 
+use chrono::format::{Item, StrftimeItems};
 use serde_derive::{Deserialize, Serialize};
+use toml::Value;
 
-use crate::Error;
+use crate::{notebooks, Context, Error};
 
-#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// How dates are shown unless `date_format` is set
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    /// Color output to terminals, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Config {
     /// The directory where notebooks are stored
     pub root: PathBuf,
+    /// The command used to edit posts, preferred over `VISUAL` and `EDITOR`
+    pub editor: Option<String>,
+    /// The notebook used by commands that aren't given one
+    pub default_notebook: Option<String>,
+    /// The command long output is shown with in a terminal
+    pub pager: Option<String>,
+    pub color: Color,
+    /// A chrono format string for dates shown to the user
+    pub date_format: String,
+    /// Other names for notebooks
+    pub aliases: BTreeMap<String, String>,
+    /// Arguments added to every use of a subcommand
+    pub defaults: BTreeMap<String, String>,
+    /// Defaults for notebook settings, which `notebook.toml` can override
+    pub notebooks: toml::value::Table,
 }
 
+/// `config.toml` as written by the user
 #[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 struct TempConfig {
-    root: String,
+    root: Option<String>,
+    editor: Option<String>,
+    default_notebook: Option<String>,
+    pager: Option<String>,
+    color: Color,
+    date_format: Option<String>,
+    aliases: BTreeMap<String, String>,
+    defaults: BTreeMap<String, String>,
+    notebooks: toml::value::Table,
+}
+
+/// Notebooks are kept in the platform's data directory unless `root` is set.
+fn default_root() -> PathBuf {
+    project_dirs()
+        .map(|dirs| dirs.data_dir().to_owned())
+        .unwrap_or_default()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root: default_root(),
+            editor: None,
+            default_notebook: None,
+            pager: None,
+            color: Color::Auto,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            aliases: BTreeMap::new(),
+            defaults: BTreeMap::new(),
+            notebooks: toml::value::Table::new(),
+        }
+    }
 }
 
 impl Config {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        Self::from_str(&fs::read_to_string(path)?)
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).context("read", path)?;
+        Self::from_str(&contents).map_err(|e| e.at(path))
     }
 
-    fn from_str(s: &str) -> Result<Self, Error> {
-        let config: TempConfig = toml::from_str(s)?;
-        let root = shellexpand::tilde(&config.root).into_owned();
+    fn from_str(s: &str) -> Result<Self, Invalid> {
+        let config: TempConfig = toml::from_str(s).map_err(Invalid::Toml)?;
+
+        let date_format = config
+            .date_format
+            .unwrap_or_else(|| DEFAULT_DATE_FORMAT.to_string());
+        if StrftimeItems::new(&date_format).any(|item| item == Item::Error) {
+            return Err(Invalid::Setting(
+                "date_format",
+                "not a valid date format".into(),
+            ));
+        }
+        for (command, args) in &config.defaults {
+            if shell_words::split(args).is_err() {
+                return Err(Invalid::Setting(
+                    "defaults",
+                    format!("unmatched quote in the arguments for {command}"),
+                ));
+            }
+        }
+        let mut notebook = notebooks::Config::new("").to_table();
+        merge(&mut notebook, config.notebooks.clone());
+        let notebook: notebooks::Config =
+            Value::Table(notebook).try_into().map_err(Invalid::Toml)?;
+        if let Err((key, reason)) = notebook.validate() {
+            return Err(Invalid::Setting("notebooks", format!("{key}: {reason}")));
+        }
+        for (alias, id) in &config.aliases {
+            if id.is_empty() {
+                return Err(Invalid::Setting(
+                    "aliases",
+                    format!("{alias} names no notebook"),
+                ));
+            }
+        }
+
         Ok(Config {
-            root: PathBuf::from(root),
+            root: match config.root {
+                Some(root) => PathBuf::from(shellexpand::tilde(&root).into_owned()),
+                None => default_root(),
+            },
+            editor: config.editor,
+            default_notebook: config.default_notebook,
+            pager: config.pager,
+            color: config.color,
+            date_format,
+            aliases: config.aliases,
+            defaults: config.defaults,
+            notebooks: config.notebooks,
         })
     }
+
+    /// Returns the notebook an alias stands for, or the name itself.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map(String::as_str).unwrap_or(name)
+    }
+
+    /// The arguments set in `[defaults]` for a subcommand
+    pub fn default_args(&self, command: &str) -> Vec<String> {
+        self.defaults
+            .get(command)
+            .and_then(|args| shell_words::split(args).ok())
+            .unwrap_or_default()
+    }
+
+    /// Every setting with its value, as it would be written in `config.toml`
+    pub fn list(&self) -> Vec<(String, Value)> {
+        let mut settings = vec![(
+            "root".to_string(),
+            Value::String(self.root.to_string_lossy().into_owned()),
+        )];
+        for (key, value) in [
+            ("editor", &self.editor),
+            ("default_notebook", &self.default_notebook),
+            ("pager", &self.pager),
+        ] {
+            if let Some(value) = value {
+                settings.push((key.to_string(), Value::String(value.clone())));
+            }
+        }
+        let color = match self.color {
+            Color::Auto => "auto",
+            Color::Always => "always",
+            Color::Never => "never",
+        };
+        settings.push(("color".to_string(), Value::String(color.to_string())));
+        settings.push((
+            "date_format".to_string(),
+            Value::String(self.date_format.clone()),
+        ));
+        for (table, map) in [("aliases", &self.aliases), ("defaults", &self.defaults)] {
+            for (key, value) in map {
+                settings.push((format!("{table}.{key}"), Value::String(value.clone())));
+            }
+        }
+        for (key, value) in &self.notebooks {
+            settings.push((format!("notebooks.{key}"), value.clone()));
+        }
+        settings
+    }
+
+    /// The value of a setting such as `editor` or `aliases.j`
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.list()
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Why a configuration is invalid, before the file it is in is known
+enum Invalid {
+    Toml(toml::de::Error),
+    Setting(&'static str, String),
+}
+
+impl Invalid {
+    fn at(self, path: &Path) -> Error {
+        let path = path.to_owned();
+        match self {
+            Invalid::Toml(source) => Error::Config { path, source },
+            Invalid::Setting(key, reason) => Error::Setting { path, key, reason },
+        }
+    }
 }
 
 #[test]
@@ -47,11 +245,88 @@ fn load_from_str() {
     let s = r#"root = "~/our/nice/directory""#;
     let home = std::env::var("HOME").unwrap();
     assert_eq!(
-        Config::from_str(s).unwrap(),
+        Config::from_str(s).ok().unwrap(),
         Config {
             root: PathBuf::from(format!("{home}/our/nice/directory")),
+            ..Default::default()
         }
     );
+    assert_eq!(Config::from_str("").ok().unwrap(), Config::default());
+
+    let config = Config::from_str(
+        "color = \"never\"\n[aliases]\nj = \"journal\"\n[defaults]\npost = \"--template 'my day'\"",
+    )
+    .ok()
+    .unwrap();
+    assert_eq!(config.resolve("j"), "journal");
+    assert_eq!(config.default_args("post"), vec!["--template", "my day"]);
+    assert_eq!(config.get("color"), Some(Value::String("never".into())));
+
+    assert!(matches!(
+        Config::from_str("rot = \"x\""),
+        Err(Invalid::Toml(_))
+    ));
+    assert!(matches!(
+        Config::from_str("date_format = \"%Q\""),
+        Err(Invalid::Setting("date_format", _))
+    ));
+    assert!(matches!(
+        Config::from_str("[notebooks]\ntimezone = \"Mars/Olympus\""),
+        Err(Invalid::Setting("notebooks", _))
+    ));
+}
+
+/// Changes a setting in `config.toml`, such as `editor` or `aliases.j`. With
+/// no value, the setting is removed. The file is only written if the result
+/// is valid. Comments in the file are not kept.
+pub fn set(key: &str, value: Option<&str>) -> Result<(), Error> {
+    let path = get_path(Some("config.toml"))?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).context("read", &path),
+    };
+    let mut file: toml::value::Table =
+        toml::from_str(&contents).map_err(|source| Error::Config {
+            path: path.clone(),
+            source,
+        })?;
+
+    let (table, key) = match key.split_once('.') {
+        Some((table, key)) => {
+            let table = file
+                .entry(table.to_string())
+                .or_insert_with(|| Value::Table(Default::default()));
+            match table {
+                Value::Table(table) => (table, key),
+                _ => {
+                    return Err(Error::Setting {
+                        path,
+                        key: "config",
+                        reason: format!("{table} is not a table"),
+                    })
+                }
+            }
+        }
+        None => (&mut file, key),
+    };
+    match value {
+        // Values are TOML, but plain words don't need quotes
+        Some(value) => {
+            let value = toml::from_str::<toml::value::Table>(&format!("v = {value}"))
+                .ok()
+                .and_then(|mut t| t.remove("v"))
+                .unwrap_or_else(|| Value::String(value.to_string()));
+            table.insert(key.to_string(), value);
+        }
+        None => {
+            table.remove(key);
+        }
+    }
+
+    let contents = to_string(file)?;
+    Config::from_str(&contents).map_err(|e| e.at(&path))?;
+    fs::write(&path, contents).context("write", &path)
 }
 
 /// Returns the configuration, initializing it if not existent
@@ -60,29 +335,63 @@ pub fn get() -> Result<Config, Error> {
 
     let config_path = get_path(Some("config.toml"))?;
     if !config_path.exists() {
-        fs::write(&config_path, default_config.as_bytes())?;
+        fs::write(&config_path, default_config.as_bytes()).context("create", &config_path)?;
     }
 
     let config = Config::load(config_path)?;
     Ok(config)
 }
 
+fn project_dirs() -> Result<directories::ProjectDirs, Error> {
+    directories::ProjectDirs::from("dev", "Just Josias", "Just Write").ok_or(Error::Dirs)
+}
+
 /// Returns the configuration directory based on directories-rs with an optional subpath.
 /// Creates the configuration directory if it doesn't exist. Does not create subpath.
 pub fn get_path(subpath: Option<&str>) -> Result<PathBuf, Error> {
-    let dirs = match directories::ProjectDirs::from("dev", "Just Josias", "Just Write") {
-        Some(dirs) => dirs,
-        None => {
-            return Err(Error::Dirs);
-        }
-    };
+    let mut path = project_dirs()?.config_dir().to_owned();
+    if !path.exists() {
+        fs::create_dir_all(&path).context("create", &path)?;
+    }
+    if let Some(p) = subpath {
+        path.push(p);
+    }
+    Ok(path)
+}
 
-    let mut path = dirs.config_dir().to_owned();
+/// Returns the cache directory based on directories-rs with an optional subpath.
+/// Creates the cache directory if it doesn't exist. Does not create subpath.
+pub fn get_cache_path(subpath: Option<&str>) -> Result<PathBuf, Error> {
+    let mut path = project_dirs()?.cache_dir().to_owned();
     if !path.exists() {
-        fs::create_dir_all(&path)?;
+        fs::create_dir_all(&path).context("create", &path)?;
     }
     if let Some(p) = subpath {
         path.push(p);
     }
     Ok(path)
 }
+
+/// Writes a table as TOML. Tables have to come after plain values.
+pub(crate) fn to_string(table: toml::value::Table) -> Result<String, Error> {
+    let (tables, values): (toml::value::Table, toml::value::Table) =
+        table.into_iter().partition(|(_, value)| value.is_table());
+    let mut contents = toml::to_string(&values)?;
+    if !tables.is_empty() {
+        contents.push('\n');
+        contents.push_str(&toml::to_string(&tables)?);
+    }
+    Ok(contents)
+}
+
+/// Sets every value of `layer` in `base`, merging tables key by key.
+pub(crate) fn merge(base: &mut toml::value::Table, layer: toml::value::Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
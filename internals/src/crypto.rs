@@ -0,0 +1,104 @@
+//! Encryption for notebooks with `encrypted = true`
+//!
+//! Posts are encrypted with [age](https://age-encryption.org) to an X25519
+//! key belonging to the notebook. The key is either kept in the notebook as
+//! `key.age`, itself encrypted with a passphrase, or in a separate key file
+//! named by `key_file` in `notebook.toml`.
+//!
+//! Decrypting the key with a passphrase is deliberately slow, so it is done
+//! once when the notebook is unlocked rather than for every post.
+
+use std::{fs, path::Path, str::FromStr};
+
+use age::{
+    scrypt,
+    secrecy::{ExposeSecret, SecretString},
+    x25519,
+};
+
+use crate::{Context, Error};
+
+/// The header every age file starts with
+const MAGIC: &[u8] = b"age-encryption.org/";
+
+/// Returns whether the contents of a file are encrypted.
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+fn error<E: std::fmt::Display>(e: E) -> Error {
+    Error::Crypto(e.to_string())
+}
+
+pub struct Key(x25519::Identity);
+
+impl Key {
+    pub fn generate() -> Self {
+        Self(x25519::Identity::generate())
+    }
+
+    /// Reads an unencrypted age identity file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path).context("read", path)?;
+        let line = contents
+            .lines()
+            .find(|l| l.starts_with("AGE-SECRET-KEY-"))
+            .ok_or_else(|| Error::Crypto(format!("no key found in {}", path.display())))?;
+        Ok(Self(x25519::Identity::from_str(line).map_err(error)?))
+    }
+
+    /// Writes the key to an identity file only readable by the current user.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        write_private(path, self.0.to_string().expose_secret().as_bytes())
+    }
+
+    /// Decrypts a key that was saved with [`Key::save_with_passphrase`].
+    pub fn load_with_passphrase(path: &Path, passphrase: &str) -> Result<Self, Error> {
+        let identity = scrypt::Identity::new(SecretString::from(passphrase.to_string()));
+        let key = age::decrypt(&identity, &fs::read(path).context("read", path)?).map_err(error)?;
+        let key = String::from_utf8(key).map_err(error)?;
+        Ok(Self(x25519::Identity::from_str(key.trim()).map_err(error)?))
+    }
+
+    pub fn save_with_passphrase(&self, path: &Path, passphrase: &str) -> Result<(), Error> {
+        let recipient = scrypt::Recipient::new(SecretString::from(passphrase.to_string()));
+        let key = age::encrypt(&recipient, self.0.to_string().expose_secret().as_bytes())
+            .map_err(error)?;
+        fs::write(path, key).context("write", path)
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        age::encrypt(&self.0.to_public(), plaintext).map_err(error)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        age::decrypt(&self.0, ciphertext).map_err(error)
+    }
+}
+
+/// Writes a file that only the current user can read.
+pub(crate) fn write_private(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut f| f.write_all(contents))
+        .context("write", path)
+}
+
+#[test]
+fn round_trip() {
+    let key = Key::generate();
+    let ciphertext = key.encrypt(b"secret post").unwrap();
+    assert!(is_encrypted(&ciphertext));
+    assert!(!is_encrypted(b"---\ntimestamp: 2022-05-11\n---\n"));
+    assert_eq!(key.decrypt(&ciphertext).unwrap(), b"secret post");
+    assert!(Key::generate().decrypt(&ciphertext).is_err());
+}
@@ -0,0 +1,160 @@
+//! Launching the user's text editor
+//!
+//! The editor is the first one set of:
+//! 1. `editor` in the notebook's `notebook.toml`, when editing a notebook's posts
+//! 2. `editor` in `config.toml`
+//! 3. the `VISUAL` environment variable
+//! 4. the `EDITOR` environment variable
+//! 5. `vi`
+//!
+//! Commands are split like a shell would, so quoted paths and arguments work
+//! (`EDITOR='"/opt/My Editor/edit" -n'`). Graphical editors that return
+//! immediately and keep running in the background, such as VS Code, are
+//! given their flag to wait for the file to be closed.
+
+use std::{env, fs, io, path::Path, process::Command};
+
+use crate::{config, Context, Error, Notebook};
+
+pub const DEFAULT_EDITOR: &str = "vi";
+
+/// Editors that fork unless told to wait, with the flag that makes them wait
+const WAIT_FLAGS: &[(&str, &[&str])] = &[
+    ("atom", &["--wait", "-w"]),
+    ("code", &["--wait", "-w"]),
+    ("code-insiders", &["--wait", "-w"]),
+    ("codium", &["--wait", "-w"]),
+    ("gedit", &["--wait", "-w"]),
+    ("gvim", &["--nofork", "-f"]),
+    ("kate", &["--block", "-b"]),
+    ("mate", &["--wait", "-w"]),
+    ("mvim", &["--nofork", "-f"]),
+    ("subl", &["--wait", "-w"]),
+    ("zed", &["--wait", "-w"]),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum EditorError {
+    #[error("editor command is empty")]
+    Empty,
+    #[error("can't parse editor command {0:?}: unmatched quote")]
+    Parse(String),
+    #[error("editor {0:?} not found. Set VISUAL, EDITOR or editor in config.toml")]
+    NotFound(String),
+    #[error("failed to run editor {0:?}: {1}")]
+    Launch(String, io::Error),
+    #[error("editor {0:?} exited with {1}")]
+    Failed(String, String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Editor {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Editor {
+    /// Parses an editor command such as `code --wait`.
+    pub fn parse(command: &str) -> Result<Self, EditorError> {
+        let mut words = shell_words::split(command)
+            .map_err(|_| EditorError::Parse(command.to_string()))?
+            .into_iter();
+        let program = words.next().ok_or(EditorError::Empty)?;
+        let mut args: Vec<String> = words.collect();
+
+        let name = Path::new(&program)
+            .file_stem()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if let Some((_, flags)) = WAIT_FLAGS.iter().find(|(editor, _)| *editor == name) {
+            if !args.iter().any(|arg| flags.contains(&arg.as_str())) {
+                args.insert(0, flags[0].to_string());
+            }
+        }
+
+        Ok(Self { program, args })
+    }
+
+    /// The editor chosen by the user, see the module documentation.
+    pub fn get() -> Result<Self, Error> {
+        Self::choose(config::get()?.editor)
+    }
+
+    /// The editor for a notebook's posts, see the module documentation.
+    pub fn for_notebook(notebook: &Notebook) -> Result<Self, Error> {
+        Self::choose(notebook.read_config()?.editor)
+    }
+
+    fn choose(configured: Option<String>) -> Result<Self, Error> {
+        let command = configured
+            .into_iter()
+            .chain(env::var("VISUAL"))
+            .chain(env::var("EDITOR"))
+            .find(|c| !c.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
+        Ok(Self::parse(&command)?)
+    }
+
+    /// Opens `path` and waits for the editor to exit successfully.
+    pub fn open(&self, path: &Path) -> Result<(), EditorError> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .arg(path)
+            .status()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => EditorError::NotFound(self.program.clone()),
+                _ => EditorError::Launch(self.program.clone(), e),
+            })?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(EditorError::Failed(
+                self.program.clone(),
+                status.to_string(),
+            ))
+        }
+    }
+}
+
+/// Opens `path` in the user's editor and returns its contents once closed.
+pub fn edit(path: &Path) -> Result<String, Error> {
+    Editor::get()?.open(path)?;
+    fs::read_to_string(path).context("read", path)
+}
+
+/// Like [`edit`], with the editor set for `notebook`.
+pub fn edit_for(notebook: &Notebook, path: &Path) -> Result<String, Error> {
+    Editor::for_notebook(notebook)?.open(path)?;
+    fs::read_to_string(path).context("read", path)
+}
+
+#[test]
+fn parse_commands() {
+    assert_eq!(
+        Editor::parse(r#""/opt/My Editor/edit" -n 'a b'"#).unwrap(),
+        Editor {
+            program: "/opt/My Editor/edit".to_string(),
+            args: vec!["-n".to_string(), "a b".to_string()],
+        }
+    );
+    assert_eq!(Editor::parse("code").unwrap().args, vec!["--wait"]);
+    assert_eq!(Editor::parse("subl -w").unwrap().args, vec!["-w"]);
+    assert!(matches!(Editor::parse("  "), Err(EditorError::Empty)));
+    assert!(matches!(Editor::parse("'vim"), Err(EditorError::Parse(_))));
+}
+
+#[cfg(unix)]
+#[test]
+fn exit_status() {
+    let path = Path::new("/dev/null");
+    assert!(Editor::parse("true").unwrap().open(path).is_ok());
+    assert!(matches!(
+        Editor::parse("false").unwrap().open(path),
+        Err(EditorError::Failed(..))
+    ));
+    assert!(matches!(
+        Editor::parse("jw-no-such-editor").unwrap().open(path),
+        Err(EditorError::NotFound(_))
+    ));
+}
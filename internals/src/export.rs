@@ -0,0 +1,78 @@
+//! Exporters for publishing notebooks in other formats
+
+pub mod epub;
+pub mod feed;
+pub mod html;
+pub mod json;
+pub mod markdown;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::{Error, Notebook, Post};
+
+/// An inclusive range of days. Unset ends are unbounded.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn contains(&self, dt: &DateTime<FixedOffset>) -> bool {
+        let date = dt.date_naive();
+        self.from.is_none_or(|from| from <= date) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+type DatedPost = (Post, DateTime<FixedOffset>);
+
+/// The posts written within `range`, oldest first. Posts without a date are skipped.
+fn dated_posts(notebook: &Notebook, range: DateRange) -> Result<Vec<DatedPost>, Error> {
+    let mut posts: Vec<_> = notebook
+        .read_posts()?
+        .into_iter()
+        .filter_map(|post| {
+            let dt = post.timestamp()?;
+            Some((post, dt))
+        })
+        .filter(|(_, dt)| range.contains(dt))
+        .collect();
+    posts.sort_by_key(|(_, dt)| *dt);
+    Ok(posts)
+}
+
+/// Escapes text for use in HTML and XML documents.
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fills in `{{key}}` placeholders in a template.
+pub(crate) fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = template.to_string();
+    for (key, value) in vars {
+        out = out.replace(&format!("{{{{{key}}}}}"), value);
+    }
+    out
+}
+
+#[test]
+fn render_template() {
+    assert_eq!(
+        render(
+            "<h1>{{title}}</h1>{{ title }}",
+            &[("title", &escape("Q&A"))]
+        ),
+        "<h1>Q&amp;A</h1>{{ title }}"
+    );
+}
@@ -0,0 +1,163 @@
+//! A whole notebook as an EPUB 3 book with a chapter for each month
+
+use std::{fs, io::Write, path::Path};
+
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use super::{dated_posts, escape, DateRange, DatedPost};
+use crate::{Context, Error, Notebook};
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+const STYLE: &str = "body { font-family: serif; line-height: 1.4; }
+h2 { font-size: 1em; color: #555; margin-top: 2em; }
+";
+
+struct Chapter<'a> {
+    id: String,
+    title: String,
+    posts: Vec<&'a DatedPost>,
+}
+
+/// Writes the posts within `range` to an EPUB file at `path`.
+pub fn export(notebook: &Notebook, range: DateRange, path: &Path) -> Result<(), Error> {
+    let config = notebook.read_config()?;
+    let posts = dated_posts(notebook, range)?;
+
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for post in &posts {
+        let id = post.1.format("%Y-%m").to_string();
+        match chapters.last_mut() {
+            Some(chapter) if chapter.id == id => chapter.posts.push(post),
+            _ => chapters.push(Chapter {
+                id,
+                title: post.1.format("%B %Y").to_string(),
+                posts: vec![post],
+            }),
+        }
+    }
+
+    let mut zip = ZipWriter::new(fs::File::create(path).context("create", path)?);
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype must come first and be uncompressed for readers to detect the format
+    add(&mut zip, "mimetype", stored, b"application/epub+zip", path)?;
+    add(
+        &mut zip,
+        "META-INF/container.xml",
+        deflated,
+        CONTAINER.as_bytes(),
+        path,
+    )?;
+    add(
+        &mut zip,
+        "OEBPS/style.css",
+        deflated,
+        STYLE.as_bytes(),
+        path,
+    )?;
+
+    let title = escape(&config.name);
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    let mut toc = String::new();
+    for chapter in &chapters {
+        manifest.push_str(&format!(
+            "<item id=\"c{0}\" href=\"{0}.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+            chapter.id
+        ));
+        spine.push_str(&format!("<itemref idref=\"c{}\"/>\n", chapter.id));
+        toc.push_str(&format!(
+            "<li><a href=\"{}.xhtml\">{}</a></li>\n",
+            chapter.id, chapter.title
+        ));
+
+        let mut body = format!("<h1>{}</h1>\n", chapter.title);
+        for (post, dt) in &chapter.posts {
+            body.push_str(&format!(
+                "<section>\n<h2>{}</h2>\n{}</section>\n",
+                dt.format("%A, %B %-d, %H:%M"),
+                post.to_html()
+            ));
+        }
+        let name = format!("OEBPS/{}.xhtml", chapter.id);
+        let chapter = xhtml(&chapter.title, &body);
+        add(&mut zip, &name, deflated, chapter.as_bytes(), path)?;
+    }
+
+    let nav = xhtml(
+        &title,
+        &format!("<nav epub:type=\"toc\">\n<h1>{title}</h1>\n<ol>\n{toc}</ol>\n</nav>\n"),
+    );
+    add(&mut zip, "OEBPS/nav.xhtml", deflated, nav.as_bytes(), path)?;
+
+    let opf = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="id">urn:jw:{id}</dc:identifier>
+<dc:title>{title}</dc:title>
+<dc:creator>{author}</dc:creator>
+<dc:description>{description}</dc:description>
+<dc:language>en</dc:language>
+<meta property="dcterms:modified">{modified}</meta>
+</metadata>
+<manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+<item id="style" href="style.css" media-type="text/css"/>
+{manifest}</manifest>
+<spine>
+{spine}</spine>
+</package>
+"#,
+        id = escape(&notebook.id),
+        author = escape(config.author.as_ref().unwrap_or(&config.name)),
+        description = escape(&config.description),
+        modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+    );
+    add(
+        &mut zip,
+        "OEBPS/content.opf",
+        deflated,
+        opf.as_bytes(),
+        path,
+    )?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Adds a file to the book being written to `path`.
+fn add(
+    zip: &mut ZipWriter<fs::File>,
+    name: &str,
+    options: FileOptions,
+    contents: &[u8],
+    path: &Path,
+) -> Result<(), Error> {
+    zip.start_file(name, options)?;
+    zip.write_all(contents).context("write", path)
+}
+
+fn xhtml(title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<title>{title}</title>
+<link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#
+    )
+}
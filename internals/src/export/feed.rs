@@ -0,0 +1,145 @@
+//! Atom and RSS feeds of the latest posts
+//!
+//! Feed metadata comes from `notebook.toml`. When the notebook has a `url`,
+//! entries link to the pages created by the HTML export under that address.
+
+use super::{dated_posts, escape, DateRange, DatedPost};
+use crate::{notebooks, Error, Notebook, Post};
+
+/// Returns an Atom 1.0 document with the `count` most recent posts.
+pub fn atom(notebook: &Notebook, count: usize) -> Result<String, Error> {
+    let (config, posts) = latest(notebook, count)?;
+    let id = feed_id(notebook, &config);
+
+    let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    feed.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    feed.push_str(&format!("<title>{}</title>\n", escape(&config.name)));
+    if !config.description.is_empty() {
+        feed.push_str(&format!(
+            "<subtitle>{}</subtitle>\n",
+            escape(&config.description)
+        ));
+    }
+    feed.push_str(&format!("<id>{}</id>\n", escape(&id)));
+    if let Some(url) = &config.url {
+        feed.push_str(&format!("<link href=\"{}\"/>\n", escape(url)));
+    }
+    let updated = posts.first().map(|(_, dt)| dt.to_rfc3339());
+    feed.push_str(&format!(
+        "<updated>{}</updated>\n",
+        updated.unwrap_or_else(|| chrono::Local::now().to_rfc3339())
+    ));
+    let author = config.author.as_ref().unwrap_or(&config.name);
+    feed.push_str(&format!(
+        "<author><name>{}</name></author>\n",
+        escape(author)
+    ));
+    feed.push_str("<generator>jw</generator>\n");
+
+    for (post, dt) in &posts {
+        feed.push_str("<entry>\n");
+        feed.push_str(&format!("<title>{}</title>\n", escape(&post.title())));
+        feed.push_str(&format!(
+            "<id>{}</id>\n",
+            escape(&entry_id(&id, &config, post))
+        ));
+        if let Some(link) = link(&config, post) {
+            feed.push_str(&format!("<link href=\"{}\"/>\n", escape(&link)));
+        }
+        feed.push_str(&format!("<updated>{}</updated>\n", dt.to_rfc3339()));
+        for tag in post.tags() {
+            feed.push_str(&format!("<category term=\"{}\"/>\n", escape(&tag)));
+        }
+        feed.push_str(&format!(
+            "<content type=\"html\">{}</content>\n",
+            escape(&post.to_html())
+        ));
+        feed.push_str("</entry>\n");
+    }
+    feed.push_str("</feed>\n");
+
+    Ok(feed)
+}
+
+/// Returns an RSS 2.0 document with the `count` most recent posts.
+pub fn rss(notebook: &Notebook, count: usize) -> Result<String, Error> {
+    let (config, posts) = latest(notebook, count)?;
+    let id = feed_id(notebook, &config);
+
+    let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    feed.push_str("<rss version=\"2.0\">\n<channel>\n");
+    feed.push_str(&format!("<title>{}</title>\n", escape(&config.name)));
+    // Without an address there is nothing to link to, so the link is left
+    // out rather than empty
+    if let Some(url) = &config.url {
+        feed.push_str(&format!("<link>{}</link>\n", escape(url)));
+    }
+    feed.push_str(&format!(
+        "<description>{}</description>\n",
+        escape(&config.description)
+    ));
+    if let Some((_, dt)) = posts.first() {
+        feed.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>\n",
+            dt.to_rfc2822()
+        ));
+    }
+    feed.push_str("<generator>jw</generator>\n");
+
+    for (post, dt) in &posts {
+        feed.push_str("<item>\n");
+        feed.push_str(&format!("<title>{}</title>\n", escape(&post.title())));
+        if let Some(link) = link(&config, post) {
+            feed.push_str(&format!("<link>{}</link>\n", escape(&link)));
+        }
+        feed.push_str(&format!(
+            "<guid isPermaLink=\"false\">{}</guid>\n",
+            escape(&entry_id(&id, &config, post))
+        ));
+        feed.push_str(&format!("<pubDate>{}</pubDate>\n", dt.to_rfc2822()));
+        for tag in post.tags() {
+            feed.push_str(&format!("<category>{}</category>\n", escape(&tag)));
+        }
+        feed.push_str(&format!(
+            "<description>{}</description>\n",
+            escape(&post.to_html())
+        ));
+        feed.push_str("</item>\n");
+    }
+    feed.push_str("</channel>\n</rss>\n");
+
+    Ok(feed)
+}
+
+/// The newest posts with a known date, newest first
+fn latest(notebook: &Notebook, count: usize) -> Result<(notebooks::Config, Vec<DatedPost>), Error> {
+    let config = notebook.read_config()?;
+    let mut posts = dated_posts(notebook, DateRange::default())?;
+    posts.reverse();
+    posts.truncate(count);
+    Ok((config, posts))
+}
+
+fn feed_id(notebook: &Notebook, config: &notebooks::Config) -> String {
+    match &config.url {
+        Some(url) => url.clone(),
+        None => format!("urn:jw:{}", notebook.id),
+    }
+}
+
+fn entry_id(feed_id: &str, config: &notebooks::Config, post: &Post) -> String {
+    match link(config, post) {
+        Some(link) => link,
+        None => format!("{}:{}", feed_id, post.slug()),
+    }
+}
+
+/// The address of the post in a site made by [`super::html::export`]
+fn link(config: &notebooks::Config, post: &Post) -> Option<String> {
+    let url = config.url.as_ref()?;
+    Some(format!(
+        "{}/posts/{}.html",
+        url.trim_end_matches('/'),
+        post.slug()
+    ))
+}
@@ -0,0 +1,242 @@
+//! Static HTML site export
+//!
+//! The generated site is self-contained and only uses relative links, so it
+//! can be browsed offline or uploaded to any static host:
+//! ```text
+//! index.html          every post, newest first
+//! posts/<slug>.html   one page per post
+//! tags/<tag>.html     posts containing a hashtag
+//! archive/<Y-m>.html  posts written in a month
+//! posts/attachments/  the attachments posts link to, decrypted
+//! ```
+//!
+//! The look of the site can be changed with a template directory. If it
+//! contains a `page.html`, that is used as the layout of every page with the
+//! placeholders `{{title}}`, `{{notebook}}`, `{{description}}`, `{{root}}`
+//! and `{{content}}`. All other files in the directory (stylesheets, images)
+//! are copied into the site as-is.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{escape, render};
+use crate::{attachments, Context, Error, Notebook, Post};
+
+const DEFAULT_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{title}} - {{notebook}}</title>
+<style>
+body { max-width: 40em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; }
+ul.posts { list-style: none; padding: 0; }
+time { color: #666; margin-right: 1em; }
+</style>
+</head>
+<body>
+<header>
+<h1><a href="{{root}}index.html">{{notebook}}</a></h1>
+<p>{{description}}</p>
+</header>
+<main>
+{{content}}
+</main>
+</body>
+</html>
+"#;
+
+/// Writes a notebook to `out_dir` as a static site, optionally using the
+/// layout and assets in `template_dir`.
+pub fn export(
+    notebook: &Notebook,
+    out_dir: &Path,
+    template_dir: Option<&Path>,
+) -> Result<(), Error> {
+    let config = notebook.read_config()?;
+
+    let mut posts = notebook.read_posts()?;
+    posts.sort_by_key(|p| std::cmp::Reverse(p.timestamp()));
+
+    let page = match template_dir {
+        Some(dir) if dir.join("page.html").exists() => {
+            let path = dir.join("page.html");
+            fs::read_to_string(&path).context("read", &path)?
+        }
+        _ => DEFAULT_PAGE.to_string(),
+    };
+    let site = Site {
+        page,
+        notebook: escape(&config.name),
+        description: escape(&config.description),
+        out_dir: out_dir.to_owned(),
+    };
+
+    for dir in ["posts", "tags", "archive"] {
+        let dir = out_dir.join(dir);
+        fs::create_dir_all(&dir).context("create", &dir)?;
+    }
+
+    let mut tagged: BTreeMap<String, Vec<&Post>> = BTreeMap::new();
+    let mut months: BTreeMap<String, Vec<&Post>> = BTreeMap::new();
+    for post in &posts {
+        for tag in post.tags() {
+            tagged.entry(tag).or_default().push(post);
+        }
+        if let Some(dt) = post.timestamp() {
+            months
+                .entry(dt.format("%Y-%m").to_string())
+                .or_default()
+                .push(post);
+        }
+
+        let content = format!(
+            "<article>\n<p>{}{}</p>\n{}</article>\n",
+            date(post)
+                .map(|d| format!("<time>{d}</time>"))
+                .unwrap_or_default(),
+            tag_links(&post.tags(), "../"),
+            post.to_html()
+        );
+        site.write(&post_page(post), &post.title(), "../", &content)?;
+    }
+
+    for (tag, posts) in &tagged {
+        let content = format!("<h2>#{}</h2>\n{}", escape(tag), post_list(posts, "../"));
+        site.write(
+            &format!("tags/{tag}.html"),
+            &format!("#{tag}"),
+            "../",
+            &content,
+        )?;
+    }
+    for (month, posts) in &months {
+        let content = format!("<h2>{month}</h2>\n{}", post_list(posts, "../"));
+        site.write(&format!("archive/{month}.html"), month, "../", &content)?;
+    }
+
+    let mut content = post_list(&posts.iter().collect::<Vec<_>>(), "");
+    if !tagged.is_empty() {
+        let mut tags: Vec<(String, usize)> = notebook.tags()?.into_iter().collect();
+        tags.sort();
+        content.push_str("<h2>Tags</h2>\n<p>");
+        for (tag, count) in tags {
+            if tagged.contains_key(&tag) {
+                let tag = escape(&tag);
+                content.push_str(&format!(
+                    "<a href=\"tags/{tag}.html\">#{tag}</a> ({count}) "
+                ));
+            }
+        }
+        content.push_str("</p>\n");
+    }
+    if !months.is_empty() {
+        content.push_str("<h2>Archive</h2>\n<ul>\n");
+        for (month, posts) in months.iter().rev() {
+            content.push_str(&format!(
+                "<li><a href=\"archive/{month}.html\">{month}</a> ({})</li>\n",
+                posts.len()
+            ));
+        }
+        content.push_str("</ul>\n");
+    }
+    site.write("index.html", &config.name, "", &content)?;
+
+    copy_attachments(notebook, &posts, out_dir)?;
+    if let Some(dir) = template_dir {
+        copy_assets(dir, out_dir)?;
+    }
+
+    Ok(())
+}
+
+struct Site {
+    page: String,
+    notebook: String,
+    description: String,
+    out_dir: PathBuf,
+}
+
+impl Site {
+    fn write(&self, path: &str, title: &str, root: &str, content: &str) -> Result<(), Error> {
+        let html = render(
+            &self.page,
+            &[
+                ("title", &escape(title)),
+                ("notebook", &self.notebook),
+                ("description", &self.description),
+                ("root", root),
+                ("content", content),
+            ],
+        );
+        let path = self.out_dir.join(path);
+        fs::write(&path, html).context("write", &path)
+    }
+}
+
+fn post_page(post: &Post) -> String {
+    format!("posts/{}.html", post.slug())
+}
+
+fn date(post: &Post) -> Option<String> {
+    post.timestamp().map(|dt| dt.format("%Y-%m-%d").to_string())
+}
+
+fn tag_links(tags: &[String], root: &str) -> String {
+    tags.iter()
+        .map(|tag| {
+            let tag = escape(tag);
+            format!(" <a href=\"{root}tags/{tag}.html\">#{tag}</a>")
+        })
+        .collect()
+}
+
+fn post_list(posts: &[&Post], root: &str) -> String {
+    let mut list = String::from("<ul class=\"posts\">\n");
+    for post in posts {
+        list.push_str(&format!(
+            "<li><time>{}</time><a href=\"{root}{}\">{}</a></li>\n",
+            date(post).unwrap_or_default(),
+            escape(&post_page(post)),
+            escape(&post.title())
+        ));
+    }
+    list.push_str("</ul>\n");
+    list
+}
+
+/// Copies the attachments that posts link to next to the post pages, where
+/// their relative links point. Missing attachments are left out.
+fn copy_attachments(notebook: &Notebook, posts: &[Post], out_dir: &Path) -> Result<(), Error> {
+    let names: BTreeSet<String> = posts.iter().flat_map(attachments::links).collect();
+    let dir = out_dir.join("posts").join(attachments::DIR);
+    for name in names {
+        let path = Path::new(attachments::DIR).join(&name);
+        // Links can't reach outside the attachments directory
+        if name.contains(['/', '\\']) || !notebook.path.join(&path).is_file() {
+            continue;
+        }
+        fs::create_dir_all(&dir).context("create", &dir)?;
+        let dest = dir.join(&name);
+        fs::write(&dest, notebook.read_bytes(&path)?).context("write", &dest)?;
+    }
+    Ok(())
+}
+
+/// Recursively copies everything except the layout into the site.
+fn copy_assets(from: &Path, to: &Path) -> Result<(), Error> {
+    for entry in fs::read_dir(from).context("read", from)? {
+        let entry = entry.context("read", from)?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type().context("read", entry.path())?.is_dir() {
+            fs::create_dir_all(&dest).context("create", &dest)?;
+            copy_assets(&entry.path(), &dest)?;
+        } else if entry.file_name() != "page.html" {
+            fs::copy(entry.path(), &dest).context("copy", entry.path())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! A whole notebook as a JSON array of parsed posts
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde_derive::Serialize;
+
+use super::{dated_posts, DateRange};
+use crate::{Error, Notebook};
+
+#[derive(Serialize)]
+struct JsonPost<'a> {
+    path: &'a Path,
+    timestamp: String,
+    title: String,
+    tags: Vec<String>,
+    metadata: &'a BTreeMap<String, String>,
+    body: &'a str,
+}
+
+/// Returns the posts within `range` as a pretty-printed JSON array, oldest first.
+pub fn export(notebook: &Notebook, range: DateRange) -> Result<String, Error> {
+    let posts = dated_posts(notebook, range)?;
+    let posts: Vec<JsonPost> = posts
+        .iter()
+        .map(|(post, dt)| JsonPost {
+            path: &post.path,
+            timestamp: dt.to_rfc3339(),
+            title: post.title(),
+            tags: post.tags(),
+            metadata: &post.metadata,
+            body: &post.body,
+        })
+        .collect();
+
+    Ok(serde_json::to_string_pretty(&posts)?)
+}
@@ -0,0 +1,28 @@
+//! A whole notebook as one Markdown document, with a heading for each day
+
+use super::{dated_posts, DateRange};
+use crate::{Error, Notebook};
+
+/// Concatenates the posts within `range` into a single Markdown document.
+pub fn export(notebook: &Notebook, range: DateRange) -> Result<String, Error> {
+    let config = notebook.read_config()?;
+
+    let mut doc = format!("# {}\n", config.name);
+    if !config.description.is_empty() {
+        doc.push_str(&format!("\n{}\n", config.description));
+    }
+
+    let mut last_day = None;
+    for (post, dt) in dated_posts(notebook, range)? {
+        let day = dt.date_naive();
+        if last_day != Some(day) {
+            doc.push_str(&format!("\n## {}\n", day.format("%A, %B %-d, %Y")));
+            last_day = Some(day);
+        }
+        doc.push_str(&format!("\n### {}\n\n", dt.format("%H:%M")));
+        doc.push_str(post.body.trim());
+        doc.push('\n');
+    }
+
+    Ok(doc)
+}
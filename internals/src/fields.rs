@@ -0,0 +1,248 @@
+//! Values like moods and hours of sleep, recorded in posts to track them
+//!
+//! Fields are written in the front matter, or on a line of their own in
+//! the text of the post with two colons:
+//! ```text
+//! ---
+//! timestamp: 2022-05-11T15:15:56+02:00
+//! mood: 4
+//! ---
+//! Slept well for once.
+//! sleep:: 7.5
+//! exercise:: yes
+//! ```
+//! Field names are case-insensitive. A field written in the text wins over
+//! the same field in the front matter.
+
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+use chrono::{DateTime, Duration, FixedOffset, Months, NaiveDate};
+
+use crate::{Error, Notebook, Post};
+
+/// The characters of a sparkline, from the lowest value to the highest
+const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Bool(bool),
+    /// Anything else, such as `weather: sunny`
+    Text(String),
+}
+
+impl Value {
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        match s.to_lowercase().as_str() {
+            "true" | "yes" => return Value::Bool(true),
+            "false" | "no" => return Value::Bool(false),
+            _ => {}
+        }
+        match s.parse::<f64>() {
+            Ok(n) if n.is_finite() => Value::Number(n),
+            _ => Value::Text(s.to_string()),
+        }
+    }
+
+    /// The value as a number, counting `true` as 1 and `false` as 0
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            Value::Text(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", if *b { "yes" } else { "no" }),
+            Value::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+/// A value of a field in one post
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    pub time: DateTime<FixedOffset>,
+    pub value: Value,
+    pub post: PathBuf,
+}
+
+/// Returns the fields of a post. The timestamp isn't a field.
+pub fn extract(post: &Post) -> BTreeMap<String, Value> {
+    let mut fields: BTreeMap<String, Value> = post
+        .metadata
+        .iter()
+        .filter(|(key, _)| key.as_str() != "timestamp")
+        .map(|(key, value)| (key.to_lowercase(), Value::parse(value)))
+        .collect();
+    for line in post.body.lines() {
+        if let Some((key, value)) = inline_field(line) {
+            fields.insert(key.to_lowercase(), Value::parse(value));
+        }
+    }
+    fields
+}
+
+/// Parses a `key:: value` line, which can also be a list item.
+fn inline_field(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    let line = line.strip_prefix("- ").unwrap_or(line);
+    let (key, value) = line.split_once("::")?;
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    Some((key, value.trim())).filter(|(_, value)| valid && !value.is_empty())
+}
+
+/// Counts the posts that have each field.
+pub fn names(notebook: &Notebook) -> Result<BTreeMap<String, usize>, Error> {
+    let mut names = BTreeMap::new();
+    for post in notebook.read_posts()? {
+        for key in extract(&post).into_keys() {
+            *names.entry(key).or_insert(0) += 1;
+        }
+    }
+    Ok(names)
+}
+
+/// Returns the values of a field in posts written on or after `since`,
+/// oldest first. Posts without a date are left out.
+pub fn series(
+    notebook: &Notebook,
+    field: &str,
+    since: Option<NaiveDate>,
+) -> Result<Vec<Point>, Error> {
+    let field = field.to_lowercase();
+    let mut points: Vec<Point> = notebook
+        .read_posts()?
+        .into_iter()
+        .filter_map(|post| {
+            let time = post.timestamp()?;
+            let value = extract(&post).remove(&field)?;
+            Some(Point {
+                time,
+                value,
+                post: post.path,
+            })
+        })
+        .filter(|point| since.is_none_or(|since| point.time.date_naive() >= since))
+        .collect();
+    points.sort_by_key(|point| point.time);
+    Ok(points)
+}
+
+/// Parses how far back to go, either as a date like `2022-05-01` or as a
+/// number of days, weeks, months or years before `today` like `30d` or `6m`.
+pub fn parse_since(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let unit = s.chars().last()?;
+    let n: u32 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'd' => today.checked_sub_signed(Duration::days(n.into())),
+        'w' => today.checked_sub_signed(Duration::weeks(n.into())),
+        'm' => today.checked_sub_months(Months::new(n)),
+        'y' => today.checked_sub_months(Months::new(n.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+/// Draws the values as a line of bars scaled between the lowest and highest
+/// value. Values that aren't numbers are left blank.
+pub fn sparkline(points: &[Point]) -> String {
+    let numbers: Vec<Option<f64>> = points.iter().map(|p| p.value.as_number()).collect();
+    let (min, max) = numbers
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &n| {
+            (min.min(n), max.max(n))
+        });
+    numbers
+        .iter()
+        .map(|n| match n {
+            None => ' ',
+            // All values are the same, so there is nothing to scale
+            Some(_) if max <= min => LEVELS[LEVELS.len() / 2],
+            Some(n) => {
+                let level = (n - min) / (max - min) * (LEVELS.len() - 1) as f64;
+                LEVELS[level.round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// Writes the values as CSV with a header, one row per post.
+pub fn to_csv(field: &str, points: &[Point]) -> String {
+    let quote = |s: &str| {
+        if s.contains([',', '"', '\n']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+    let mut csv = format!("time,{},post\n", quote(field));
+    for point in points {
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            point.time.to_rfc3339(),
+            quote(&point.value.to_string()),
+            quote(&point.post.to_string_lossy())
+        ));
+    }
+    csv
+}
+
+#[test]
+fn fields_and_series() {
+    let post = Post::parse(
+        "2022-05-11-today.md",
+        "---\ntimestamp: 2022-05-11T15:15:56+02:00\nMood: 3\nweather: sunny\n---\n\
+         Better than expected.\nmood:: 4\n- sleep:: 7.5\nexercise:: yes\nnot:: \nsee https://example.com\n",
+    );
+    let fields = extract(&post);
+    assert_eq!(fields.len(), 4);
+    assert_eq!(fields["mood"], Value::Number(4.0));
+    assert_eq!(fields["sleep"], Value::Number(7.5));
+    assert_eq!(fields["exercise"], Value::Bool(true));
+    assert_eq!(fields["weather"], Value::Text("sunny".to_string()));
+
+    let point = |value| Point {
+        time: post.timestamp().unwrap(),
+        value,
+        post: post.path.clone(),
+    };
+    let points = [
+        point(Value::Number(1.0)),
+        point(Value::Text("?".to_string())),
+        point(Value::Number(5.0)),
+        point(Value::Number(3.0)),
+    ];
+    assert_eq!(sparkline(&points), "▁ █▅");
+    assert_eq!(
+        to_csv("mood", &points[..1]),
+        "time,mood,post\n2022-05-11T15:15:56+02:00,1,2022-05-11-today.md\n"
+    );
+
+    let today = NaiveDate::from_ymd_opt(2022, 5, 11).unwrap();
+    assert_eq!(
+        parse_since("30d", today),
+        NaiveDate::from_ymd_opt(2022, 4, 11)
+    );
+    assert_eq!(
+        parse_since("1y", today),
+        NaiveDate::from_ymd_opt(2021, 5, 11)
+    );
+    assert_eq!(
+        parse_since("2022-01-01", today),
+        NaiveDate::from_ymd_opt(2022, 1, 1)
+    );
+    assert_eq!(parse_since("soon", today), None);
+}
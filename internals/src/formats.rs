@@ -0,0 +1,500 @@
+//! The markup languages posts can be written in
+//!
+//! Each [`Format`] has a [`Handler`] that knows where a post's metadata is
+//! and how to find its title, tags and links:
+//!
+//! | Format   | Extension | Metadata                                |
+//! |----------|-----------|-----------------------------------------|
+//! | Markdown | `.md`     | `---` front matter with `timestamp:`    |
+//! | Org      | `.org`    | `#+DATE:` and other `#+KEY:` lines      |
+//! | AsciiDoc | `.adoc`   | `:date:` and other header attributes    |
+//! | Text     | `.txt`    | `Date:` and other header lines, then a blank line |
+//!
+//! Whatever the format, the date of a post ends up under `timestamp` in
+//! [`Post::metadata`](crate::Post::metadata).
+
+use std::{collections::BTreeMap, path::Path};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone};
+use pulldown_cmark::Parser;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{export::escape, search};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Markdown,
+    Org,
+    #[serde(rename = "asciidoc")]
+    AsciiDoc,
+    Text,
+}
+
+impl Format {
+    /// Guesses the format of a file from its extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "md" | "markdown" => Some(Format::Markdown),
+            "org" => Some(Format::Org),
+            "adoc" | "asciidoc" => Some(Format::AsciiDoc),
+            "txt" | "text" => Some(Format::Text),
+            _ => None,
+        }
+    }
+
+    /// The extension of new post files including the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => ".md",
+            Format::Org => ".org",
+            Format::AsciiDoc => ".adoc",
+            Format::Text => ".txt",
+        }
+    }
+
+    pub fn handler(self) -> &'static dyn Handler {
+        match self {
+            Format::Markdown => &Markdown,
+            Format::Org => &Org,
+            Format::AsciiDoc => &AsciiDoc,
+            Format::Text => &Text,
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Format::Markdown),
+            "org" => Ok(Format::Org),
+            "asciidoc" | "adoc" => Ok(Format::AsciiDoc),
+            "text" | "txt" => Ok(Format::Text),
+            _ => Err(format!("unknown format {s}")),
+        }
+    }
+}
+
+/// What jw needs to know about a markup language
+pub trait Handler {
+    /// The metadata at the start of a new post written at `timestamp`
+    fn header(&self, timestamp: &str) -> String;
+
+    /// Splits a post into its metadata and its body.
+    fn split<'a>(&self, contents: &'a str) -> (BTreeMap<String, String>, &'a str);
+
+    /// The heading or first line of a post's body
+    fn title(&self, body: &str) -> Option<String>;
+
+    /// The tags in a post's body, lowercased and in order of appearance
+    fn tags(&self, body: &str) -> Vec<String> {
+        search::extract_tags(body)
+    }
+
+    /// The targets of the links and images in a post's body
+    fn links(&self, body: &str) -> Vec<String>;
+
+    /// A link to `target`, shown inline if it is an image
+    fn link(&self, target: &str, text: &str, image: bool) -> String;
+
+    fn to_html(&self, body: &str) -> String;
+}
+
+pub struct Markdown;
+
+impl Handler for Markdown {
+    fn header(&self, timestamp: &str) -> String {
+        format!("---\ntimestamp: {timestamp}\n---\n")
+    }
+
+    fn split<'a>(&self, contents: &'a str) -> (BTreeMap<String, String>, &'a str) {
+        let mut metadata = BTreeMap::new();
+        let mut body = contents;
+
+        if let Some(rest) = contents.strip_prefix("---\n") {
+            let end = if rest.starts_with("---\n") {
+                Some(0)
+            } else {
+                rest.find("\n---\n").map(|i| i + 1)
+            };
+            if let Some(end) = end {
+                for line in rest[..end].lines() {
+                    if let Some((key, value)) = line.split_once(':') {
+                        metadata.insert(key.trim().to_string(), value.trim().to_string());
+                    }
+                }
+                body = &rest[end + "---\n".len()..];
+            }
+        }
+        (metadata, body)
+    }
+
+    fn title(&self, body: &str) -> Option<String> {
+        let line = body.lines().map(str::trim).find(|l| !l.is_empty())?;
+        Some(line.trim_start_matches('#').trim().to_string())
+    }
+
+    fn links(&self, body: &str) -> Vec<String> {
+        delimited(body, "](", ")")
+    }
+
+    fn link(&self, target: &str, text: &str, image: bool) -> String {
+        let bang = if image { "!" } else { "" };
+        format!("{bang}[{text}]({target})")
+    }
+
+    fn to_html(&self, body: &str) -> String {
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, Parser::new(body));
+        html
+    }
+}
+
+pub struct Org;
+
+impl Handler for Org {
+    fn header(&self, timestamp: &str) -> String {
+        format!("#+DATE: {timestamp}\n")
+    }
+
+    fn split<'a>(&self, contents: &'a str) -> (BTreeMap<String, String>, &'a str) {
+        header_lines(contents, |line| {
+            let (key, value) = line.strip_prefix("#+")?.split_once(':')?;
+            Some((key, value))
+        })
+    }
+
+    fn title(&self, body: &str) -> Option<String> {
+        let line = body
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with('#'))?;
+        Some(org_heading_text(line).to_string())
+    }
+
+    /// Hashtags, and tags at the end of headings like `* Title :work:idea:`
+    fn tags(&self, body: &str) -> Vec<String> {
+        let mut tags = search::extract_tags(body);
+        for line in body.lines().filter(|l| l.starts_with('*')) {
+            if let Some(heading_tags) = org_heading_tags(line) {
+                tags.extend(heading_tags.split(':').map(str::to_lowercase));
+            }
+        }
+        tags
+    }
+
+    fn links(&self, body: &str) -> Vec<String> {
+        delimited(body, "[[", "]")
+            .into_iter()
+            .map(|target| target.trim_start_matches("file:").to_string())
+            .collect()
+    }
+
+    fn link(&self, target: &str, text: &str, image: bool) -> String {
+        if image {
+            format!("[[file:{target}]]")
+        } else {
+            format!("[[file:{target}][{text}]]")
+        }
+    }
+
+    fn to_html(&self, body: &str) -> String {
+        simple_html(
+            body,
+            |line| {
+                let stars = line.len() - line.trim_start_matches('*').len();
+                if stars == 0 {
+                    return None;
+                }
+                line[stars..]
+                    .strip_prefix(' ')
+                    .map(|_| (stars, org_heading_text(line)))
+            },
+            |line| line.starts_with("#+"),
+        )
+    }
+}
+
+/// A heading without its stars and tags
+fn org_heading_text(line: &str) -> &str {
+    let text = line.trim_start_matches('*').trim();
+    match org_heading_tags(text) {
+        Some(tags) => text[..text.len() - tags.len() - 2].trim_end(),
+        None => text,
+    }
+}
+
+/// The tags of a heading like `work:idea` for `* Title :work:idea:`
+fn org_heading_tags(line: &str) -> Option<&str> {
+    let last = line.trim_end().rsplit(' ').next()?;
+    let tags = last.strip_prefix(':')?.strip_suffix(':')?;
+    let valid = !tags.is_empty()
+        && tags
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ':' | '_' | '@'));
+    Some(tags).filter(|_| valid)
+}
+
+pub struct AsciiDoc;
+
+impl Handler for AsciiDoc {
+    fn header(&self, timestamp: &str) -> String {
+        format!(":date: {timestamp}\n\n")
+    }
+
+    fn split<'a>(&self, contents: &'a str) -> (BTreeMap<String, String>, &'a str) {
+        header_lines(contents, |line| {
+            let (key, value) = line.strip_prefix(':')?.split_once(": ")?;
+            Some((key, value)).filter(|(key, _)| !key.contains(' '))
+        })
+    }
+
+    fn title(&self, body: &str) -> Option<String> {
+        let line = body.lines().map(str::trim).find(|l| !l.is_empty())?;
+        Some(line.trim_start_matches('=').trim().to_string())
+    }
+
+    fn links(&self, body: &str) -> Vec<String> {
+        let mut links = delimited(body, "link:", "[");
+        links.extend(delimited(body, "image::", "["));
+        links.extend(
+            delimited(body, "image:", "[")
+                .into_iter()
+                .filter(|target| !target.starts_with(':')),
+        );
+        links
+    }
+
+    fn link(&self, target: &str, text: &str, image: bool) -> String {
+        if image {
+            format!("image::{target}[{text}]")
+        } else {
+            format!("link:{target}[{text}]")
+        }
+    }
+
+    fn to_html(&self, body: &str) -> String {
+        simple_html(
+            body,
+            |line| {
+                let level = line.len() - line.trim_start_matches('=').len();
+                if level == 0 {
+                    return None;
+                }
+                line[level..]
+                    .strip_prefix(' ')
+                    .map(|text| (level, text.trim()))
+            },
+            |line| line.starts_with("//"),
+        )
+    }
+}
+
+pub struct Text;
+
+impl Handler for Text {
+    fn header(&self, timestamp: &str) -> String {
+        format!("Date: {timestamp}\n\n")
+    }
+
+    /// Header lines only count as such when a blank line follows them.
+    fn split<'a>(&self, contents: &'a str) -> (BTreeMap<String, String>, &'a str) {
+        let header = |line: &'a str| {
+            let (key, value) = line.split_once(": ")?;
+            let valid = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '-');
+            Some((key, value)).filter(|_| valid)
+        };
+        match contents.find("\n\n") {
+            Some(end) if contents[..end].lines().all(|line| header(line).is_some()) => {
+                let (metadata, _) = header_lines(&contents[..end], header);
+                (metadata, &contents[end + 2..])
+            }
+            _ => (BTreeMap::new(), contents),
+        }
+    }
+
+    fn title(&self, body: &str) -> Option<String> {
+        let line = body.lines().map(str::trim).find(|l| !l.is_empty())?;
+        Some(line.to_string())
+    }
+
+    fn links(&self, body: &str) -> Vec<String> {
+        let trim = |word: &str| word.trim_end_matches(['.', ',', ')', ';']).to_string();
+        let mut links = Vec::new();
+        for line in body.lines() {
+            // A line ending like those written by `link`, whose text may contain spaces
+            let (line, target) = match line.trim().rsplit_once(": ") {
+                Some((rest, target))
+                    if target.contains('/') && !target.contains(char::is_whitespace) =>
+                {
+                    (rest, Some(target))
+                }
+                _ => (line, None),
+            };
+            links.extend(
+                line.split_whitespace()
+                    .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+                    .chain(target)
+                    .map(trim),
+            );
+        }
+        links
+    }
+
+    fn link(&self, target: &str, text: &str, _image: bool) -> String {
+        format!("{text}: {target}")
+    }
+
+    fn to_html(&self, body: &str) -> String {
+        simple_html(body, |_| None, |_| false)
+    }
+}
+
+/// Reads metadata from the lines at the start of a post that `parse` splits
+/// into a key and a value. Keys are lowercased, and a `date` is stored as
+/// `timestamp` if it can be read.
+fn header_lines<'a>(
+    contents: &'a str,
+    parse: impl Fn(&'a str) -> Option<(&'a str, &'a str)>,
+) -> (BTreeMap<String, String>, &'a str) {
+    let mut metadata = BTreeMap::new();
+    let mut rest = contents;
+    while !rest.is_empty() {
+        let end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        let (key, value) = match parse(rest[..end].trim_end()) {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+            None => break,
+        };
+        match parse_date(value) {
+            Some(dt) if key == "date" => metadata.insert("timestamp".into(), dt.to_rfc3339()),
+            _ => metadata.insert(key, value.to_string()),
+        };
+        rest = &rest[end..];
+    }
+    let body = rest.strip_prefix('\n').unwrap_or(rest);
+    (metadata, body)
+}
+
+/// Reads a date like `2022-05-11T15:15:56+02:00`, `2022-05-11 15:15`, or
+/// Org's `[2022-05-11 Wed 15:15]`. Dates without an offset are local.
+fn parse_date(s: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt);
+    }
+    let s = s.trim_matches(['[', ']', '<', '>']);
+    let mut words = s.split_whitespace();
+    let date = NaiveDate::parse_from_str(words.next()?, "%Y-%m-%d").ok()?;
+    let time = words
+        .find_map(|word| NaiveTime::parse_from_str(word, "%H:%M").ok())
+        .unwrap_or_default();
+    let dt = Local.from_local_datetime(&date.and_time(time)).earliest()?;
+    Some(dt.fixed_offset())
+}
+
+/// Returns the text between each `start` and the next `end`.
+fn delimited(text: &str, start: &str, end: &str) -> Vec<String> {
+    text.match_indices(start)
+        .filter_map(|(i, _)| {
+            let rest = &text[i + start.len()..];
+            let target = &rest[..rest.find(end)?];
+            Some(target.to_string()).filter(|t| !t.is_empty() && !t.contains(char::is_whitespace))
+        })
+        .collect()
+}
+
+/// Renders text as paragraphs separated by blank lines. `heading` returns
+/// the level and text of heading lines, and lines that `skip` returns true
+/// for are left out.
+fn simple_html(
+    body: &str,
+    heading: impl Fn(&str) -> Option<(usize, &str)>,
+    skip: impl Fn(&str) -> bool,
+) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let flush = |paragraph: &mut Vec<&str>, html: &mut String| {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", escape(&paragraph.join("\n"))));
+            paragraph.clear();
+        }
+    };
+    for line in body.lines() {
+        if skip(line) {
+            continue;
+        }
+        if let Some((level, text)) = heading(line) {
+            flush(&mut paragraph, &mut html);
+            let level = level.clamp(1, 6);
+            html.push_str(&format!("<h{level}>{}</h{level}>\n", escape(text)));
+        } else if line.trim().is_empty() {
+            flush(&mut paragraph, &mut html);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush(&mut paragraph, &mut html);
+    html
+}
+
+#[test]
+fn org_posts() {
+    let org = Format::Org.handler();
+    let (metadata, body) = org.split(
+        "#+DATE: [2022-05-11 Wed 15:15]\n#+TITLE: Hello\n* Today :work:Idea:\nSome #rust\n[[file:attachments/a.png]]\n",
+    );
+    assert_eq!(metadata["title"], "Hello");
+    assert!(metadata["timestamp"].starts_with("2022-05-11T15:15:00"));
+    assert_eq!(org.title(body).unwrap(), "Today");
+    assert_eq!(org.tags(body), vec!["rust", "work", "idea"]);
+    assert_eq!(org.links(body), vec!["attachments/a.png"]);
+    assert_eq!(
+        org.to_html(body),
+        "<h1>Today</h1>\n<p>Some #rust\n[[file:attachments/a.png]]</p>\n"
+    );
+    assert_eq!(
+        org.to_html("- item\n  - nested\n"),
+        "<p>- item\n  - nested</p>\n"
+    );
+}
+
+#[test]
+fn text_and_asciidoc_posts() {
+    let text = Format::Text.handler();
+    let (metadata, body) =
+        text.split("Date: 2022-05-11T15:15:56+02:00\nMood: 4\n\nNote: not a header\n");
+    assert_eq!(metadata["timestamp"], "2022-05-11T15:15:56+02:00");
+    assert_eq!(metadata["mood"], "4");
+    assert_eq!(body, "Note: not a header\n");
+    assert_eq!(text.split(body).0.len(), 0);
+    let body = format!(
+        "{}\nsee https://example.com, or {}\n",
+        text.link("attachments/a.png", "my photo.png", true),
+        text.link("attachments/b.pdf", "b.pdf", false)
+    );
+    assert_eq!(
+        text.links(&body),
+        vec![
+            "attachments/a.png",
+            "https://example.com",
+            "attachments/b.pdf"
+        ]
+    );
+
+    let adoc = Format::AsciiDoc.handler();
+    let (metadata, body) =
+        adoc.split(":date: 2022-05-11T15:15:56+02:00\n\n= Hello\nimage::attachments/a.png[a]\n");
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(adoc.title(body).unwrap(), "Hello");
+    assert_eq!(adoc.links(body), vec!["attachments/a.png"]);
+    assert_eq!(
+        adoc.to_html("== Plan\n* item\n  indented\n"),
+        "<h2>Plan</h2>\n<p>* item\n  indented</p>\n"
+    );
+
+    assert_eq!(Format::from_path("a/b.ORG"), Some(Format::Org));
+    assert_eq!("adoc".parse::<Format>(), Ok(Format::AsciiDoc));
+}
@@ -0,0 +1,107 @@
+//! Checking that a notebook's files agree with its indexes
+
+use std::{collections::BTreeSet, fmt, path::PathBuf};
+
+use crate::{attachments, Error, Notebook};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// A post in `posts.txt` whose file doesn't exist
+    MissingPost(PathBuf),
+    /// An attachment that a post links to, or that is listed in
+    /// `attachments.txt`, but doesn't exist
+    MissingAttachment { name: String, post: Option<PathBuf> },
+    /// A file in `attachments/` that no post links to
+    OrphanedAttachment(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingPost(post) => write!(f, "missing post {}", post.display()),
+            Problem::MissingAttachment {
+                name,
+                post: Some(post),
+            } => write!(
+                f,
+                "missing attachment {} linked from {}",
+                name,
+                post.display()
+            ),
+            Problem::MissingAttachment { name, post: None } => {
+                write!(f, "missing attachment {}", name)
+            }
+            Problem::OrphanedAttachment(name) => {
+                write!(f, "attachment {} isn't linked from any post", name)
+            }
+        }
+    }
+}
+
+/// Looks for posts and attachments that are missing or left over.
+/// Links in posts that can't be read aren't checked.
+pub fn check(notebook: &Notebook) -> Result<Vec<Problem>, Error> {
+    let mut problems = Vec::new();
+    let files: BTreeSet<String> = attachments::files(notebook)?.into_iter().collect();
+    let mut linked = BTreeSet::new();
+
+    for post in notebook.get_posts()? {
+        if !notebook.path.join(&post).exists() {
+            problems.push(Problem::MissingPost(post));
+            continue;
+        }
+        let parsed = match notebook.read_post(&post) {
+            Ok(parsed) => parsed,
+            Err(Error::Locked) => return Err(Error::Locked),
+            Err(_) => continue,
+        };
+        for name in attachments::links(&parsed) {
+            if !files.contains(&name) {
+                problems.push(Problem::MissingAttachment {
+                    name: name.clone(),
+                    post: Some(post.clone()),
+                });
+            }
+            linked.insert(name);
+        }
+    }
+
+    for attachment in attachments::list(notebook)? {
+        if !files.contains(&attachment.name) && !linked.contains(&attachment.name) {
+            problems.push(Problem::MissingAttachment {
+                name: attachment.name,
+                post: None,
+            });
+        }
+    }
+    for name in files.difference(&linked) {
+        problems.push(Problem::OrphanedAttachment(name.clone()));
+    }
+    Ok(problems)
+}
+
+#[test]
+fn text_notebook_with_attachment() {
+    let config = crate::notebooks::Config {
+        post_path: "%Y-%m-%d-%s.txt".to_string(),
+        ..crate::notebooks::Config::new("fsck-text")
+    };
+    let notebook = crate::notebooks::test_notebook("fsck-text", config);
+    let photo = notebook.path.join("my photo.png");
+    std::fs::write(&photo, "png").unwrap();
+    let attachment = notebook.attach(&photo).unwrap();
+    notebook
+        .post(&format!(
+            "Hello\n{}\n",
+            attachment.link(crate::formats::Format::Text)
+        ))
+        .unwrap();
+    assert_eq!(check(&notebook).unwrap(), vec![]);
+
+    std::fs::write(notebook.path.join("attachments/left.png"), "png").unwrap();
+    assert_eq!(
+        check(&notebook).unwrap(),
+        vec![Problem::OrphanedAttachment("left.png".to_string())]
+    );
+    std::fs::remove_dir_all(&notebook.path).unwrap();
+}
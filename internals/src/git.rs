@@ -0,0 +1,130 @@
+//! Git integration for notebooks with `git = true`
+//!
+//! This calls the `git` executable, so the user's own configuration
+//! (identity, credentials, hooks) applies to every commit and push.
+
+use std::{path::Path, process::Command};
+
+use crate::Error;
+
+/// Runs git in `dir`, returning its standard output.
+fn git(dir: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| Error::Git(format!("failed to run git: {e}")))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(Error::Git(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Whether `dir` has a repository of its own. One that it is only part of,
+/// such as a root directory kept in git, doesn't count.
+pub fn is_repo(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+/// Creates a repository in `dir` if there isn't one yet.
+pub fn init(dir: &Path) -> Result<(), Error> {
+    if !is_repo(dir) {
+        git(dir, &["init", "-q"])?;
+    }
+    Ok(())
+}
+
+/// Commits every change in `dir`. Does nothing if there are no changes.
+pub fn commit(dir: &Path, message: &str) -> Result<(), Error> {
+    init(dir)?;
+    git(dir, &["add", "-A"])?;
+    if git(dir, &["status", "--porcelain"])?.trim().is_empty() {
+        return Ok(());
+    }
+    git(dir, &["commit", "-q", "-m", message])?;
+    Ok(())
+}
+
+/// Pulls and rebases onto `remote`, then pushes the current branch to it.
+///
+/// `remote` can be the name of an existing remote or a URL, which is saved as
+/// `origin`. An `origin` the user set up for another URL is left alone.
+pub fn sync(dir: &Path, remote: &str) -> Result<(), Error> {
+    let remotes = git(dir, &["remote"])?;
+    let name = if remotes.lines().any(|r| r == remote) {
+        remote
+    } else if remotes.lines().any(|r| r == "origin") {
+        let url = git(dir, &["remote", "get-url", "origin"])?;
+        if url.trim() != remote {
+            return Err(Error::Git(format!(
+                "origin points to {}, not to the remote {remote}",
+                url.trim()
+            )));
+        }
+        "origin"
+    } else {
+        git(dir, &["remote", "add", "origin", remote])?;
+        "origin"
+    };
+
+    let branch = git(dir, &["symbolic-ref", "--short", "HEAD"])?;
+    let branch = branch.trim();
+
+    // A new remote has nothing to pull yet
+    if !git(dir, &["ls-remote", "--heads", name, branch])?
+        .trim()
+        .is_empty()
+    {
+        git(dir, &["pull", "-q", "--rebase", name, branch])?;
+    }
+    git(dir, &["push", "-q", "-u", name, &format!("HEAD:{branch}")])?;
+    Ok(())
+}
+
+#[test]
+fn sync_with_bare_repo() {
+    let tmp = std::env::temp_dir().join(format!("jw-git-test-{}", std::process::id()));
+    let (remote, a, b) = (tmp.join("remote.git"), tmp.join("a"), tmp.join("b"));
+    std::fs::create_dir_all(&remote).unwrap();
+    std::fs::create_dir_all(&a).unwrap();
+    git(&remote, &["init", "-q", "--bare"]).unwrap();
+    // A notebook inside another repository still gets its own
+    git(&tmp, &["init", "-q"]).unwrap();
+    let remote = remote.to_string_lossy();
+
+    let identify = |dir: &Path| {
+        git(dir, &["config", "user.name", "jw"]).unwrap();
+        git(dir, &["config", "user.email", "jw@example.com"]).unwrap();
+    };
+
+    init(&a).unwrap();
+    assert!(is_repo(&a));
+    identify(&a);
+    std::fs::write(a.join("one.md"), "one").unwrap();
+    commit(&a, "Add one.md").unwrap();
+    sync(&a, &remote).unwrap();
+
+    git(&tmp, &["clone", "-q", &remote, "b"]).unwrap();
+    identify(&b);
+    std::fs::write(b.join("two.md"), "two").unwrap();
+    commit(&b, "Add two.md").unwrap();
+    sync(&b, "origin").unwrap();
+
+    std::fs::write(a.join("three.md"), "three").unwrap();
+    commit(&a, "Add three.md").unwrap();
+    sync(&a, &remote).unwrap();
+    assert!(a.join("two.md").exists());
+    assert!(sync(&a, "/elsewhere.git").is_err());
+
+    sync(&b, "origin").unwrap();
+    assert!(b.join("three.md").exists());
+
+    std::fs::remove_dir_all(tmp).unwrap();
+}
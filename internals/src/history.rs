@@ -0,0 +1,176 @@
+//! Earlier versions of posts, for notebooks that don't use git
+//!
+//! Before a post is changed, its contents are saved in the notebook's
+//! `.history` directory:
+//! ```text
+//! .history/
+//!     objects/<SHA-256 of the contents>
+//!     posts/<path of the post>    a line of `<time> <hash>` for each revision
+//! ```
+//! Objects are copies of the post file, so posts of encrypted notebooks stay
+//! encrypted, and a version that was saved before is only stored once.
+
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, Local};
+use sha2::{Digest, Sha256};
+
+use crate::{Context, Error, Notebook};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    /// When the post was changed after this version
+    pub time: DateTime<FixedOffset>,
+    pub hash: String,
+}
+
+fn history_dir(notebook: &Notebook) -> PathBuf {
+    notebook.path.join(".history")
+}
+
+fn log_path(notebook: &Notebook, post: &Path) -> PathBuf {
+    history_dir(notebook).join("posts").join(post)
+}
+
+fn object_path(notebook: &Notebook, hash: &str) -> PathBuf {
+    history_dir(notebook).join("objects").join(hash)
+}
+
+/// Saves the current version of a post before it is changed. Does nothing
+/// for notebooks that use git, or if this version is already the latest
+/// revision. Returns whether a revision was added.
+pub fn snapshot(notebook: &Notebook, post: &Path) -> Result<bool, Error> {
+    if notebook.read_config()?.git {
+        return Ok(false);
+    }
+    let path = notebook.path.join(post);
+    let contents = fs::read(&path).context("read", &path)?;
+    let hash = format!("{:x}", Sha256::digest(&contents));
+    if list(notebook, post)?
+        .last()
+        .is_some_and(|rev| rev.hash == hash)
+    {
+        return Ok(false);
+    }
+
+    let object = object_path(notebook, &hash);
+    if !object.exists() {
+        let dir = object.parent().unwrap();
+        fs::create_dir_all(dir).context("create", dir)?;
+        fs::write(&object, &contents).context("write", &object)?;
+    }
+
+    let log = log_path(notebook, post);
+    let dir = log.parent().unwrap();
+    fs::create_dir_all(dir).context("create", dir)?;
+    let mut f = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log)
+        .context("open", &log)?;
+    writeln!(f, "{} {hash}", Local::now().to_rfc3339()).context("write", &log)?;
+    Ok(true)
+}
+
+/// Returns the saved revisions of a post, oldest first.
+pub fn list(notebook: &Notebook, post: &Path) -> Result<Vec<Revision>, Error> {
+    let log = log_path(notebook, post);
+    let text = match fs::read_to_string(&log) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("read", log),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let (time, hash) = line.split_once(' ')?;
+            Some(Revision {
+                time: DateTime::parse_from_rfc3339(time).ok()?,
+                hash: hash.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Reads the text of a revision, decrypting it if needed.
+pub fn read(notebook: &Notebook, revision: &Revision) -> Result<String, Error> {
+    notebook.read_text(object_path(notebook, &revision.hash))
+}
+
+/// Puts a revision of a post back in its place. The current version is
+/// saved first, so restoring can be taken back too.
+pub fn restore(notebook: &Notebook, post: &Path, revision: &Revision) -> Result<(), Error> {
+    snapshot(notebook, post)?;
+    let object = object_path(notebook, &revision.hash);
+    fs::copy(&object, notebook.path.join(post)).context("copy", &object)?;
+    notebook.commit(&format!("Restore post {}", post.display()))
+}
+
+/// Moves the revisions of a post that was renamed along with it.
+pub(crate) fn rename(notebook: &Notebook, old: &Path, new: &Path) -> Result<(), Error> {
+    let log = log_path(notebook, old);
+    if !log.exists() {
+        return Ok(());
+    }
+    let new_log = log_path(notebook, new);
+    let dir = new_log.parent().unwrap();
+    fs::create_dir_all(dir).context("create", dir)?;
+    fs::rename(&log, &new_log).context("move", &log)
+}
+
+/// Returns the paths of all stored revisions, such as to encrypt them.
+pub(crate) fn objects(notebook: &Notebook) -> Result<Vec<PathBuf>, Error> {
+    let dir = history_dir(notebook).join("objects");
+    match fs::read_dir(&dir) {
+        Ok(entries) => Ok(entries.filter_map(|e| e.ok()).map(|e| e.path()).collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).context("read", dir),
+    }
+}
+
+/// Compares two texts line by line. Lines only in `old` start with `-`,
+/// lines only in `new` with `+`, and lines in both with a space.
+pub fn diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            lines.push(format!("+ {}", new[j]));
+            j += 1;
+        } else {
+            lines.push(format!("- {}", old[i]));
+            i += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+#[test]
+fn diff_lines() {
+    assert_eq!(diff("a\nb\nc\n", "a\nc\nd\n"), "  a\n- b\n  c\n+ d");
+    assert_eq!(diff("", "new"), "+ new");
+    assert_eq!(diff("same", "same"), "  same");
+}
@@ -0,0 +1,386 @@
+//! Importers for posts written with other journaling tools
+//!
+//! Supported sources:
+//! * `jrnl`: a plain-text (`jrnl --export txt`) or JSON (`jrnl --export json`) export
+//! * `dayone`: the `Journal.json` of a Day One JSON export
+//! * `nb`: an nb notebook directory
+//! * `dir`: any directory of Markdown or text files
+//!
+//! Entries keep their original time and tags and are written with
+//! [`Notebook::post_at`], oldest first, then committed together.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde_derive::Deserialize;
+
+use crate::{search, Context, Error, Notebook, Post};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Jrnl,
+    DayOne,
+    Nb,
+    Directory,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jrnl" => Ok(Self::Jrnl),
+            "dayone" => Ok(Self::DayOne),
+            "nb" => Ok(Self::Nb),
+            "dir" => Ok(Self::Directory),
+            _ => Err(Error::Import(format!("unknown format: {s}"))),
+        }
+    }
+}
+
+/// A post read from another tool
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub time: DateTime<FixedOffset>,
+    pub text: String,
+    /// Tags without a leading `#` or `@`
+    pub tags: Vec<String>,
+}
+
+impl Entry {
+    /// The text with any tags that aren't already written as hashtags appended.
+    pub fn text_with_tags(&self) -> String {
+        let existing = search::extract_tags(&self.text);
+        let missing: Vec<String> = self
+            .tags
+            .iter()
+            .filter(|tag| !existing.contains(&tag.to_lowercase()))
+            .map(|tag| format!("#{tag}"))
+            .collect();
+
+        let mut text = self.text.trim_end().to_string();
+        if !missing.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&missing.join(" "));
+        }
+        text.push('\n');
+        text
+    }
+}
+
+/// Reads every entry from `source`, oldest first.
+pub fn read(format: Format, source: &Path) -> Result<Vec<Entry>, Error> {
+    let mut entries = match format {
+        Format::Jrnl => {
+            let contents = fs::read_to_string(source).context("read", source)?;
+            if contents.trim_start().starts_with('{') {
+                jrnl_json(&contents)?
+            } else {
+                jrnl_text(&contents)
+            }
+        }
+        Format::DayOne => day_one(&fs::read_to_string(source).context("read", source)?)?,
+        Format::Nb => nb(source)?,
+        Format::Directory => directory(source)?,
+    };
+    entries.sort_by_key(|e| e.time);
+    Ok(entries)
+}
+
+/// Imports every entry from `source` into the notebook, returning how many were written.
+pub fn import(notebook: &Notebook, format: Format, source: &Path) -> Result<usize, Error> {
+    let entries = read(format, source)?;
+    for entry in &entries {
+        notebook.post_at(&entry.text_with_tags(), entry.time)?;
+    }
+    notebook.commit(&format!("Import {} posts", entries.len()))?;
+    Ok(entries.len())
+}
+
+fn local(dt: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    Local
+        .from_local_datetime(&dt)
+        .earliest()
+        .map(|dt| dt.into())
+}
+
+/// Turns a tag from another app into a hashtag name. Hashtags end at
+/// whitespace and punctuation, so those are left out, along with the `@` jrnl
+/// puts in front of tags. Returns `None` if nothing is left.
+fn tag_name(tag: &str) -> Option<String> {
+    let name: String = tag
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_ascii_punctuation())
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Parses the header of a jrnl plain-text entry: `[2022-05-11 15:15] Title`
+fn jrnl_header(line: &str) -> Option<(NaiveDateTime, &str)> {
+    let line = line.strip_prefix('[').unwrap_or(line);
+    let stamp = line.get(..16)?;
+    let dt = NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M").ok()?;
+    let rest = line[16..].strip_prefix(']').unwrap_or(&line[16..]);
+    Some((dt, rest.trim_start_matches(" *").trim_start()))
+}
+
+fn jrnl_text(contents: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut current: Option<(NaiveDateTime, String)> = None;
+
+    for line in contents.lines() {
+        if let Some((dt, title)) = jrnl_header(line) {
+            entries.extend(current.take());
+            current = Some((dt, format!("{title}\n")));
+        } else if let Some((_, text)) = &mut current {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    entries.extend(current);
+
+    entries
+        .into_iter()
+        .filter_map(|(dt, text)| {
+            // jrnl marks tags with @ by default
+            let tags = text
+                .split_whitespace()
+                .filter(|w| w.starts_with('@') && w.len() > 1)
+                .filter_map(tag_name)
+                .collect();
+            Some(Entry {
+                time: local(dt)?,
+                text: text.trim().to_string(),
+                tags,
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct JrnlExport {
+    entries: Vec<JrnlEntry>,
+}
+
+#[derive(Deserialize)]
+struct JrnlEntry {
+    date: String,
+    time: String,
+    title: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn jrnl_json(contents: &str) -> Result<Vec<Entry>, Error> {
+    let export: JrnlExport = serde_json::from_str(contents)?;
+    export
+        .entries
+        .into_iter()
+        .map(|e| {
+            let stamp = format!("{} {}", e.date, e.time);
+            let time = NaiveDateTime::parse_from_str(&stamp, "%Y-%m-%d %H:%M")
+                .ok()
+                .and_then(local)
+                .ok_or_else(|| Error::Import(format!("invalid jrnl date: {stamp}")))?;
+            let text = if e.body.trim().is_empty() {
+                e.title
+            } else {
+                format!("{}\n{}", e.title, e.body.trim_end())
+            };
+            Ok(Entry {
+                time,
+                text,
+                tags: e.tags.iter().filter_map(|t| tag_name(t)).collect(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct DayOneExport {
+    entries: Vec<DayOneEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DayOneEntry {
+    creation_date: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn day_one(contents: &str) -> Result<Vec<Entry>, Error> {
+    let export: DayOneExport = serde_json::from_str(contents)?;
+    export
+        .entries
+        .into_iter()
+        .map(|e| {
+            let time = DateTime::parse_from_rfc3339(&e.creation_date)
+                .map_err(|_| Error::Import(format!("invalid Day One date: {}", e.creation_date)))?;
+            Ok(Entry {
+                time: time.with_timezone(&Local).into(),
+                // Day One escapes Markdown punctuation in its exports
+                text: e.text.replace("\\.", ".").replace("\\-", "-"),
+                tags: e.tags.iter().filter_map(|t| tag_name(t)).collect(),
+            })
+        })
+        .collect()
+}
+
+/// The time a file was written: from its front matter, a date in its name, or
+/// its modification time.
+fn file_time(post: &Post, path: &Path) -> Result<DateTime<FixedOffset>, Error> {
+    if let Some(date) = post.metadata.get("date") {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(date) {
+            return Ok(dt);
+        }
+        if let Some(dt) = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .and_then(|d| local(d.and_hms_opt(0, 0, 0)?))
+        {
+            return Ok(dt);
+        }
+    }
+    if let Some(dt) = post.timestamp() {
+        return Ok(dt);
+    }
+    let modified: DateTime<Local> = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .context("read", path)?
+        .into();
+    Ok(modified.into())
+}
+
+/// Reads a Markdown or text file, keeping `tags` from its front matter.
+fn read_file(path: &Path) -> Result<Entry, Error> {
+    let post = Post::parse(path, &fs::read_to_string(path).context("read", path)?);
+    let tags = match post.metadata.get("tags") {
+        Some(tags) => tags
+            .trim_matches(['[', ']'].as_ref())
+            .split(',')
+            .filter_map(tag_name)
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok(Entry {
+        time: file_time(&post, path)?,
+        text: post.body.trim().to_string(),
+        tags,
+    })
+}
+
+fn is_text(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("md" | "markdown" | "txt")
+    )
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir).context("read", dir)? {
+        let path = entry.context("read", dir)?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else if is_text(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn directory(source: &Path) -> Result<Vec<Entry>, Error> {
+    let mut files = Vec::new();
+    walk(source, &mut files)?;
+    files.iter().map(|path| read_file(path)).collect()
+}
+
+/// nb names notes after the time they were made (`20220511151556.md`) and
+/// lists them in a `.index` file.
+fn nb(source: &Path) -> Result<Vec<Entry>, Error> {
+    let index = source.join(".index");
+    let files: Vec<PathBuf> = if index.exists() {
+        fs::read_to_string(&index)
+            .context("read", &index)?
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| source.join(l))
+            .filter(|p| p.is_file() && is_text(p))
+            .collect()
+    } else {
+        let mut files = Vec::new();
+        walk(source, &mut files)?;
+        files
+    };
+
+    files
+        .iter()
+        .map(|path| {
+            let mut entry = read_file(path)?;
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            if let Some(dt) = stem
+                .get(..14)
+                .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%S").ok())
+                .and_then(local)
+            {
+                entry.time = dt;
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+#[test]
+fn parse_jrnl_text() {
+    let entries = jrnl_text(
+        "[2022-05-11 15:15] Went for a walk. @outside\nIt was nice. @self-care\n\n[2022-05-12 09:00] * Starred\n",
+    );
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        entries[0].text,
+        "Went for a walk. @outside\nIt was nice. @self-care"
+    );
+    assert_eq!(entries[0].tags, vec!["outside", "selfcare"]);
+    assert_eq!(
+        entries[0].text_with_tags(),
+        "Went for a walk. @outside\nIt was nice. @self-care\n\n#outside #selfcare\n"
+    );
+    assert_eq!(entries[1].text, "Starred");
+}
+
+#[test]
+fn parse_day_one() {
+    let entries = day_one(
+        r#"{"entries": [{"creationDate": "2022-05-11T13:15:56Z", "text": "Hi\\. #work", "tags": ["work", "long tag", "self-care", "?"]}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        entries[0].time,
+        DateTime::parse_from_rfc3339("2022-05-11T13:15:56Z").unwrap()
+    );
+    assert_eq!(
+        entries[0].text_with_tags(),
+        "Hi. #work\n\n#longtag #selfcare\n"
+    );
+}
+
+#[test]
+fn missing_source() {
+    let source = Path::new("/nonexistent/Journal.json");
+    let e = read(Format::DayOne, source).unwrap_err();
+    assert!(matches!(&e, Error::File { path, .. } if path == source));
+    assert!(e.to_string().contains("/nonexistent/Journal.json"));
+}
@@ -1,24 +1,112 @@
+pub mod attachments;
+pub mod calendar;
 pub mod config;
+pub mod crypto;
+pub mod editor;
+pub mod export;
+pub mod fields;
+pub mod formats;
+pub mod fsck;
+pub mod git;
+pub mod history;
+pub mod import;
 pub mod notebooks;
+pub mod posts;
+pub mod quick;
+pub mod review;
+pub mod scratch;
 pub mod search;
+pub mod stats;
+pub mod tasks;
+pub mod templates;
+pub mod trash;
+pub mod undo;
 
 mod time;
 
 pub use config::Config;
 pub use notebooks::Notebook;
+pub use posts::Post;
+
+use std::path::{Path, PathBuf};
 
 use thiserror::Error as ThisError;
 
 #[derive(Debug, ThisError)]
 pub enum Error {
-    #[error("IO Error!")]
-    IO(#[from] std::io::Error),
-    #[error("Parse Error!")]
-    Deserialize(#[from] toml::de::Error),
-    #[error("Parse Error!")]
+    #[error("{0}")]
+    IO(std::io::Error),
+    /// An I/O error with the file it happened to
+    #[error("failed to {action} {}: {source}", path.display())]
+    File {
+        action: &'static str,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid configuration in {}: {source}", path.display())]
+    Config {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid {key} in {}: {reason}", path.display())]
+    Setting {
+        path: PathBuf,
+        key: &'static str,
+        reason: String,
+    },
+    #[error("failed to write configuration: {0}")]
     Serialize(#[from] toml::ser::Error),
-    #[error("Dirs Error!")]
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("failed to write archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("can't find the home directory")]
     Dirs,
-    #[error("Exists Error!")]
-    Exists,
+    #[error("notebook {0} already exists")]
+    Exists(String),
+    #[error("notebook {0} doesn't exist")]
+    NotebookNotFound(String),
+    #[error("{0:?} can't be the name of a notebook")]
+    InvalidName(String),
+    #[error("the list of posts {} is missing", .0.display())]
+    MissingIndex(PathBuf),
+    #[error("import failed: {0}")]
+    Import(String),
+    #[error("git: {0}")]
+    Git(String),
+    /// A post was saved, but committing it failed. It is committed along
+    /// with the next change.
+    #[error("saved {}, but couldn't commit it: {source}", path.display())]
+    Uncommitted { path: PathBuf, source: Box<Error> },
+    #[error("encryption: {0}")]
+    Crypto(String),
+    #[error("trash: {0}")]
+    Trash(String),
+    #[error("task: {0}")]
+    Task(String),
+    #[error("{0:?} can't be a tag")]
+    InvalidTag(String),
+    #[error(transparent)]
+    Editor(#[from] editor::EditorError),
+    #[error("no template called {0}")]
+    TemplateNotFound(String),
+    #[error("invalid template {0}: {1}")]
+    InvalidTemplate(String, String),
+    #[error("notebook is locked")]
+    Locked,
+}
+
+/// Adds the file and what was being done with it to I/O errors.
+pub trait Context<T> {
+    fn context<P: AsRef<Path>>(self, action: &'static str, path: P) -> Result<T, Error>;
+}
+
+impl<T> Context<T> for std::io::Result<T> {
+    fn context<P: AsRef<Path>>(self, action: &'static str, path: P) -> Result<T, Error> {
+        self.map_err(|source| Error::File {
+            action,
+            path: path.as_ref().to_owned(),
+            source,
+        })
+    }
 }
@@ -1,25 +1,154 @@
 use std::{
+    collections::HashMap,
     fs,
     io::{BufRead, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, FixedOffset, Local, Utc,
+};
 use serde_derive::{Deserialize, Serialize};
+use toml::Value;
 
-use crate::{config, search, time::Timestamp, Error};
+use crate::{
+    attachments::{self, Attachment},
+    config, crypto,
+    formats::Format,
+    git, history,
+    posts::Post,
+    search,
+    time::Timestamp,
+    trash, undo, Context, Error,
+};
 
+/// The settings of a notebook. [`Notebook::read_config`] layers `notebook.toml`
+/// over the defaults in the global `config.toml`, so any of these can be set
+/// for every notebook in its `[notebooks]` table.
 #[derive(Default, Deserialize, Serialize)]
 pub struct Config {
     pub name: String,
     pub description: String,
     pub post_path: String,
+    /// The markup posts are written in. Without one, it follows the
+    /// extension of `post_path`.
+    pub format: Option<Format>,
     pub metadata: bool,
+    /// The author named in exported feeds
+    pub author: Option<String>,
+    /// The address the notebook is published at, used for links in feeds
+    pub url: Option<String>,
+    /// Commit every change to a git repository in the notebook
+    #[serde(default)]
+    pub git: bool,
+    /// The git remote used by `sync`, either a remote name or a URL
+    pub remote: Option<String>,
+    /// Store posts encrypted
+    #[serde(default)]
+    pub encrypted: bool,
+    /// An age identity file holding the key of an encrypted notebook.
+    /// Without one, the key is stored in the notebook protected by a passphrase.
+    pub key_file: Option<String>,
+    /// The template new posts start from, unless another is chosen
+    pub template: Option<String>,
+    /// The editor for this notebook, instead of the global `editor`
+    pub editor: Option<String>,
+    /// How dates are shown, instead of the global `date_format`
+    pub date_format: Option<String>,
+    /// The time zone posts are dated in, such as `Europe/Berlin`, instead of the system's
+    pub timezone: Option<String>,
+    /// Hidden from the list of notebooks, without being deleted
+    #[serde(default)]
+    pub archived: bool,
+    /// Collect the lines written with `jw repl` in one post per day,
+    /// instead of a post for each line
+    #[serde(default)]
+    pub daily_file: bool,
+}
+
+impl Config {
+    /// The extension of post files including the dot, such as `.md`
+    pub fn extension(&self) -> String {
+        match Path::new(&self.post_path).extension() {
+            Some(ext) => format!(".{}", ext.to_string_lossy()),
+            None => String::new(),
+        }
+    }
+
+    /// The format of new posts
+    pub fn post_format(&self) -> Format {
+        self.format
+            .or_else(|| Format::from_path(&self.post_path))
+            .unwrap_or_default()
+    }
+
+    /// The configuration given to new notebooks
+    pub fn new(id: &str) -> Self {
+        Self {
+            name: id.to_string(),
+            description: String::new(),
+            post_path: "%Y-%m-%d-%s.md".to_string(),
+            metadata: true,
+            ..Default::default()
+        }
+    }
+
+    /// The settings as a table, as they would be written to `notebook.toml`
+    pub fn to_table(&self) -> toml::value::Table {
+        match Value::try_from(self) {
+            Ok(Value::Table(table)) => table,
+            _ => unreachable!("the configuration is a struct of plain values"),
+        }
+    }
+
+    /// The current time in the notebook's time zone
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        match self.timezone.as_deref().map(parse_timezone) {
+            Some(Ok(tz)) => Utc::now().with_timezone(&tz).fixed_offset(),
+            _ => Local::now().fixed_offset(),
+        }
+    }
+
+    /// Checks the settings that can't be checked by their type, returning the
+    /// first invalid one and why.
+    pub(crate) fn validate(&self) -> Result<(), (&'static str, String)> {
+        if let Some(Err(reason)) = self.timezone.as_deref().map(parse_timezone) {
+            return Err(("timezone", reason));
+        }
+        if let Some(format) = &self.date_format {
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(("date_format", "not a valid date format".into()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_timezone(name: &str) -> Result<chrono_tz::Tz, String> {
+    name.parse()
+        .map_err(|_| format!("unknown time zone {name}"))
+}
+
+/// What [`Notebook::info`] found out about a notebook
+pub struct Info {
+    pub config: Config,
+    pub posts: usize,
+    pub drafts: usize,
+    /// The number of different tags used
+    pub tags: usize,
+    /// The size of all files in the notebook in bytes
+    pub size: u64,
+    pub first: Option<DateTime<FixedOffset>>,
+    pub last: Option<DateTime<FixedOffset>>,
 }
 
 #[derive(Default)]
 pub struct Notebook {
     pub id: String,
     pub path: PathBuf,
+    /// The key of an encrypted notebook, once unlocked
+    key: Option<crypto::Key>,
 }
 
 impl Notebook {
@@ -30,58 +159,257 @@ impl Notebook {
                 // We already checked for the filename, and assume the path is valid UTF-8
                 id: path.file_name().unwrap().to_string_lossy().into_owned(),
                 path: path.to_owned(),
+                key: None,
             })
         } else {
             None
         }
     }
 
-    /// Loads the notebook from the root directory
-    pub fn load(id: &str) -> Option<Self> {
-        if let Ok(config) = config::get() {
-            Self::load_from_path(&config.root.join(id))
-        } else {
-            None
-        }
+    /// Loads the notebook from the root directory. `id` can also be an alias.
+    pub fn load(id: &str) -> Result<Self, Error> {
+        let config = config::get()?;
+        Self::load_from_path(&config.root.join(config.resolve(id)))
+            .ok_or_else(|| Error::NotebookNotFound(id.to_string()))
     }
 
     /// Generates a new notebook. Fails if notebook alrady exists.
     pub fn generate(id: &str) -> Result<Notebook, Error> {
+        Self::generate_with(id, Config::new(id))
+    }
+
+    /// Generates a new notebook with the given configuration.
+    /// Fails if notebook alrady exists.
+    pub fn generate_with(id: &str, config: Config) -> Result<Notebook, Error> {
+        check_id(id)?;
         let root = config::get()?.root;
 
         let path = root.join(id);
         if path.exists() {
-            return Err(Error::Exists);
+            return Err(Error::Exists(id.to_string()));
         }
-        fs::create_dir_all(&path)?;
+        fs::create_dir_all(&path).context("create", &path)?;
 
-        let config = Config {
-            name: id.to_string(),
-            description: String::new(),
-            post_path: "%Y-%m-%d-%s.md".to_string(),
-            metadata: true,
-        };
+        // Only settings that differ from the defaults are written, so the rest
+        // can be changed for every notebook in the global configuration
+        let defaults = Config::new(id).to_table();
+        let table = config
+            .to_table()
+            .into_iter()
+            .filter(|(key, value)| {
+                matches!(key.as_str(), "name" | "description") || defaults.get(key) != Some(value)
+            })
+            .collect();
+        let config_path = path.join("notebook.toml");
+        fs::write(&config_path, config::to_string(table)?).context("write", &config_path)?;
+        let index = path.join("posts.txt");
+        fs::write(&index, "").context("write", &index)?;
 
-        let mut f = fs::File::create(path.join("notebook.toml"))?;
-        f.write_all(toml::to_string(&config)?.as_bytes())?;
+        let notebook = Notebook::load(id)?;
+        if config.git {
+            git::init(&notebook.path)?;
+            notebook.commit(&format!("Create notebook {id}"))?;
+        }
+        Ok(notebook)
+    }
 
-        // Errors are returned earlier, so unwrapping is okay
-        Ok(Notebook::load(id).unwrap())
+    /// Reads the notebook's own settings from `notebook.toml`.
+    fn read_local_config(&self) -> Result<toml::value::Table, Error> {
+        let path = self.path.join("notebook.toml");
+        let config_str = fs::read_to_string(&path).context("read", &path)?;
+        toml::from_str(&config_str).map_err(|source| Error::Config { path, source })
     }
 
+    /// Returns the notebook's settings: `notebook.toml` layered over the global
+    /// `editor`, `date_format` and `[notebooks]` table, and the built-in defaults.
     pub fn read_config(&self) -> Result<Config, Error> {
-        let config_str = fs::read_to_string(self.path.join("notebook.toml"))?;
-        Ok(toml::from_str(&config_str)?)
-    }
-    fn _write_config(&self, conf: Config) -> Result<(), Error> {
-        fs::write(
-            self.path.join("notebook.toml"),
-            toml::to_string(&conf)?.as_bytes(),
-        )?;
+        let global = config::get()?;
+        let mut table = Config::new(&self.id).to_table();
+        if let Some(editor) = global.editor {
+            table.insert("editor".to_string(), Value::String(editor));
+        }
+        table.insert("date_format".to_string(), Value::String(global.date_format));
+        config::merge(&mut table, global.notebooks);
+        config::merge(&mut table, self.read_local_config()?);
+
+        let path = self.path.join("notebook.toml");
+        let config: Config = Value::Table(table)
+            .try_into()
+            .map_err(|source| Error::Config {
+                path: path.clone(),
+                source,
+            })?;
+        match config.validate() {
+            Ok(()) => Ok(config),
+            Err((key, reason)) => Err(Error::Setting { path, key, reason }),
+        }
+    }
+
+    /// Changes settings in `notebook.toml`, removing those set to `None`.
+    /// Other settings in the file are kept as they are.
+    pub fn set_config(&self, settings: &[(&str, Option<Value>)]) -> Result<(), Error> {
+        let mut table = self.read_local_config()?;
+        for (key, value) in settings {
+            match value {
+                Some(value) => table.insert(key.to_string(), value.clone()),
+                None => table.remove(*key),
+            };
+        }
+        let path = self.path.join("notebook.toml");
+        fs::write(&path, config::to_string(table)?).context("write", &path)
+    }
+
+    pub fn set_name(&self, name: &str) -> Result<(), Error> {
+        self.set_config(&[("name", Some(Value::String(name.to_string())))])?;
+        self.commit(&format!("Name notebook {name}"))
+    }
+
+    pub fn set_description(&self, description: &str) -> Result<(), Error> {
+        let description = Value::String(description.to_string());
+        self.set_config(&[("description", Some(description))])?;
+        self.commit("Change description")
+    }
+
+    /// Hides the notebook from [`list`], or shows it again. Its posts can
+    /// still be read and written.
+    pub fn archive(&self, archived: bool) -> Result<(), Error> {
+        if archived {
+            self.set_config(&[("archived", Some(Value::Boolean(true)))])?;
+            self.commit("Archive notebook")
+        } else {
+            self.set_config(&[("archived", None)])?;
+            self.commit("Unarchive notebook")
+        }
+    }
+
+    /// Moves the notebook to a new id. Its name follows, unless it was
+    /// changed to something other than the id.
+    pub fn rename(&mut self, id: &str) -> Result<(), Error> {
+        check_id(id)?;
+        let path = self.path.with_file_name(id);
+        if path.exists() {
+            return Err(Error::Exists(id.to_string()));
+        }
+        fs::rename(&self.path, &path).context("move", &self.path)?;
+
+        let old_id = std::mem::replace(&mut self.id, id.to_string());
+        self.path = path;
+        undo::rename_notebook(&old_id, id)?;
+        trash::rename_notebook(&old_id, id)?;
+        let name = self.read_local_config()?.remove("name");
+        if name.as_ref().and_then(Value::as_str) == Some(old_id.as_str()) {
+            self.set_name(id)?;
+        }
         Ok(())
     }
 
+    /// Moves the notebook to the trash, from where it can be restored.
+    pub fn delete(self) -> Result<trash::Entry, Error> {
+        let entry = trash::put(&self.path, trash::origin(trash::Kind::Notebook, &self.id))?;
+        undo::record(undo::Operation::Delete {
+            entry: entry.id.clone(),
+        })?;
+        Ok(entry)
+    }
+
+    /// Counts the posts, drafts and tags of the notebook and finds the dates
+    /// of its first and last posts.
+    pub fn info(&self) -> Result<Info, Error> {
+        let posts = self.read_posts()?;
+        let mut tags: Vec<String> = posts.iter().flat_map(|post| post.tags()).collect();
+        tags.sort_unstable();
+        tags.dedup();
+        let dates = posts.iter().filter_map(|post| post.timestamp());
+
+        Ok(Info {
+            config: self.read_config()?,
+            posts: posts.len(),
+            drafts: self.list_drafts()?.len(),
+            tags: tags.len(),
+            size: dir_size(&self.path)?,
+            first: dates.clone().min(),
+            last: dates.max(),
+        })
+    }
+
+    /// Writes a post dated now and commits it. If only the commit fails,
+    /// the post is still saved and [`Error::Uncommitted`] is returned.
     pub fn post(&self, text: &str) -> Result<PathBuf, Error> {
+        let now = self.read_config()?.now();
+        let path = self.write_post(text, Timestamp::from_datetime(&now))?;
+        undo::record(undo::Operation::Post {
+            notebook: self.id.clone(),
+            path: path.strip_prefix(&self.path).unwrap_or(&path).to_owned(),
+        })?;
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        match self.commit(&format!("Add post {name}")) {
+            Ok(()) => Ok(path),
+            Err(e) => Err(Error::Uncommitted {
+                path,
+                source: Box::new(e),
+            }),
+        }
+    }
+
+    /// Moves a post to the trash, from where it can be restored.
+    /// `path` is relative to the notebook.
+    pub fn delete_post<P: AsRef<Path>>(&self, path: P) -> Result<trash::Entry, Error> {
+        let entry = self.trash_post(path.as_ref())?;
+        undo::record(undo::Operation::Delete {
+            entry: entry.id.clone(),
+        })?;
+        Ok(entry)
+    }
+
+    /// Like [`Notebook::delete_post`], without recording it in the undo journal.
+    pub(crate) fn trash_post(&self, path: &Path) -> Result<trash::Entry, Error> {
+        let mut posts = self.get_posts()?;
+        let index = posts.iter().position(|post| post == path);
+        if let Some(i) = index {
+            posts.remove(i);
+        }
+
+        let origin = trash::Origin {
+            path: path.to_owned(),
+            index,
+            ..trash::origin(trash::Kind::Post, &self.id)
+        };
+        let entry = trash::put(&self.path.join(path), origin)?;
+        self.write_index(&posts)?;
+        self.commit(&format!("Delete post {}", path.display()))?;
+        undo::forget(&undo::Operation::Post {
+            notebook: self.id.clone(),
+            path: path.to_owned(),
+        })?;
+        Ok(entry)
+    }
+
+    /// Adds a post back to `posts.txt` at `index`, or at the end.
+    pub(crate) fn insert_post(&self, path: &Path, index: Option<usize>) -> Result<(), Error> {
+        let mut posts = self.get_posts()?;
+        let index = index.unwrap_or(posts.len()).min(posts.len());
+        posts.insert(index, path.to_owned());
+        self.write_index(&posts)
+    }
+
+    fn write_index(&self, posts: &[PathBuf]) -> Result<(), Error> {
+        let mut text = String::new();
+        for post in posts {
+            text.push_str(&post.to_string_lossy());
+            text.push('\n');
+        }
+        let index = self.path.join("posts.txt");
+        fs::write(&index, text).context("write", &index)
+    }
+
+    /// Writes a post dated at `time` instead of now, such as when importing old posts.
+    /// The post isn't committed, so that many can be committed at once.
+    pub fn post_at(&self, text: &str, time: DateTime<FixedOffset>) -> Result<PathBuf, Error> {
+        self.write_post(text, Timestamp::from_datetime(&time))
+    }
+
+    fn write_post(&self, text: &str, dt: Timestamp) -> Result<PathBuf, Error> {
         let mut first_text = String::new();
         for (i, ch) in text.chars().enumerate() {
             first_text.push(ch);
@@ -108,13 +436,54 @@ impl Notebook {
             ft
         };
 
-        let dt = Timestamp::now();
+        let config = self.read_config()?;
+        let full_path = self.new_post_path(&config, &first_text, &dt, None);
+
+        let index = self.path.join("posts.txt");
+        let mut f1 = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index)
+            .context("open", &index)?;
+
+        // unwrap is okay because full_path was constructed with a safe filename.
+        // Written filename could be inaccurate if is not valid Unicode.
+        let name = full_path.file_name().unwrap().to_string_lossy();
+        writeln!(f1, "{name}").context("write", &index)?;
+
+        let mut contents = String::new();
+        if config.metadata {
+            contents.push_str(&config.post_format().handler().header(&dt.timestamp));
+        }
+        contents.push_str(text);
+        self.write_text(&full_path, &contents)?;
+        Ok(full_path)
+    }
+
+    /// Returns a path for a post that isn't taken yet, following `post_path`.
+    /// `first_text` names the post unless the notebook is encrypted. A post
+    /// being renamed keeps its `current` path if that is already the one it would get.
+    fn new_post_path(
+        &self,
+        config: &Config,
+        first_text: &str,
+        dt: &Timestamp,
+        current: Option<&Path>,
+    ) -> PathBuf {
+        // The filename shouldn't give away what an encrypted post says
+        let first_text = if config.encrypted {
+            dt.timestamp
+                .get(11..19)
+                .unwrap_or_default()
+                .replace(':', "")
+        } else {
+            first_text.to_string()
+        };
+
         let year = format!("{:04}", dt.year);
         let month = format!("{:02}", dt.month);
         let day = format!("{:02}", dt.day);
 
-        let config = self.read_config()?;
-
         let path = PathBuf::from(
             config
                 .post_path
@@ -123,81 +492,452 @@ impl Notebook {
                 .replace("%d", &day)
                 .replace("%s", &first_text),
         );
-        let metadata = format!("---\ntimestamp: {}\n---\n", dt.timestamp);
 
         let mut full_path = self.path.join(&path);
-        if full_path.exists() {
-            // TODO find a reasonable way to alter a path
-            full_path = full_path
-                .components()
-                .map(|c| {
-                    if c.as_os_str() == path.as_os_str() {
-                        let mut path = path.as_os_str().to_owned();
-                        path.push(".1");
-                        path
-                    } else {
-                        c.as_os_str().to_owned()
-                    }
-                })
-                .collect();
+        // TODO find a reasonable way to alter a path
+        let mut n = 1;
+        while full_path.exists() && Some(full_path.as_path()) != current {
+            let mut path = path.as_os_str().to_owned();
+            path.push(format!(".{n}"));
+            full_path = self.path.join(path);
+            n += 1;
         }
+        full_path
+    }
 
-        let mut f1 = fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .append(true)
-            .open(self.path.join("posts.txt"))?;
+    /// Commits all changes in the notebook if it uses git.
+    pub fn commit(&self, message: &str) -> Result<(), Error> {
+        if self.read_config()?.git {
+            git::commit(&self.path, message)?;
+        }
+        Ok(())
+    }
 
-        // unwrap is okay because full_path was constructed with a safe filename.
-        // Written filename could be inaccurate if is not valid Unicode.
-        f1.write_all(full_path.file_name().unwrap().to_string_lossy().as_bytes())?;
-        f1.write_all(b"\n")?;
+    /// Pulls changes from the notebook's git remote and pushes local commits to it.
+    pub fn sync(&self) -> Result<(), Error> {
+        let config = self.read_config()?;
+        if !config.git {
+            return Err(Error::Git(format!("{} doesn't use git", self.id)));
+        }
+        let remote = config
+            .remote
+            .ok_or_else(|| Error::Git(format!("no remote configured for {}", self.id)))?;
 
-        let mut f = fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .append(true)
-            .open(&full_path)?;
+        self.commit("Save changes before sync")?;
+        git::sync(&self.path, &remote)
+    }
 
-        if config.metadata {
-            f.write_all(metadata.as_bytes())?;
+    /// Saves text as a draft, which is kept out of the list of posts, searches
+    /// and tags until it is published. Returns the path of the draft relative
+    /// to the notebook.
+    pub fn save_draft(&self, text: &str) -> Result<PathBuf, Error> {
+        let dir = self.path.join("drafts");
+        fs::create_dir_all(&dir).context("create", &dir)?;
+
+        let ext = self.read_config()?.extension();
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let mut path = PathBuf::from(format!("drafts/{stamp}{ext}"));
+        let mut n = 1;
+        while self.path.join(&path).exists() {
+            path = PathBuf::from(format!("drafts/{stamp}-{n}{ext}"));
+            n += 1;
         }
-        f.write_all(text.as_bytes())?;
 
-        Ok(full_path)
+        self.write_text(&path, text)?;
+        self.commit(&format!("Save draft {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Replaces the text of an existing draft. This is called often while
+    /// typing, so the change is only committed along with the next one.
+    pub fn update_draft<P: AsRef<Path>>(&self, draft: P, text: &str) -> Result<(), Error> {
+        let draft = draft.as_ref();
+        if !self.path.join(draft).exists() {
+            return Err(Error::File {
+                action: "update",
+                path: self.path.join(draft),
+                source: std::io::ErrorKind::NotFound.into(),
+            });
+        }
+        self.write_text(draft, text)
+    }
+
+    /// Returns the paths of all drafts relative to the notebook, oldest first.
+    pub fn list_drafts(&self) -> Result<Vec<PathBuf>, Error> {
+        let path = self.path.join("drafts");
+        let dir = match fs::read_dir(&path) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("read", path),
+        };
+
+        let mut drafts: Vec<PathBuf> = dir
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| Path::new("drafts").join(entry.file_name()))
+            .collect();
+        drafts.sort();
+        Ok(drafts)
     }
 
+    /// Publishes a draft as a post written now, returning the path of the post.
+    pub fn publish_draft<P: AsRef<Path>>(&self, draft: P) -> Result<PathBuf, Error> {
+        let draft = draft.as_ref();
+        let text = self.read_text(draft)?;
+        // Once the post is saved the draft has to go, even if it wasn't committed
+        let posted = self.post(&text);
+        if matches!(posted, Ok(_) | Err(Error::Uncommitted { .. })) {
+            fs::remove_file(self.path.join(draft)).context("remove", self.path.join(draft))?;
+        }
+        let path = posted?;
+        self.commit(&format!("Publish draft {}", draft.display()))?;
+        Ok(path)
+    }
+
+    pub fn delete_draft<P: AsRef<Path>>(&self, draft: P) -> Result<(), Error> {
+        let draft = draft.as_ref();
+        fs::remove_file(self.path.join(draft)).context("remove", self.path.join(draft))?;
+        self.commit(&format!("Delete draft {}", draft.display()))
+    }
+
+    /// Returns the paths of all posts relative to the notebook, as listed in
+    /// `posts.txt`, oldest first.
     pub fn get_posts(&self) -> Result<Vec<PathBuf>, Error> {
-        let f = fs::File::open(self.path.join("posts.txt"))?;
+        let index = self.path.join("posts.txt");
+        let f = match fs::File::open(&index) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::MissingIndex(index))
+            }
+            Err(e) => return Err(e).context("open", index),
+        };
         let reader = std::io::BufReader::new(f);
         let mut posts = Vec::new();
-        for line in reader.lines().flatten() {
+        for line in reader.lines().map_while(Result::ok) {
             posts.push(PathBuf::from(line));
         }
 
         Ok(posts)
     }
 
-    fn absolute_paths(&self) -> Vec<PathBuf> {
-        self.get_posts()
-            .unwrap()
+    /// Reads and parses every post in the notebook, in the order they were written.
+    /// Skips posts that can't be read.
+    pub fn read_posts(&self) -> Result<Vec<Post>, Error> {
+        let format = self.read_config()?.post_format();
+        let posts = self
+            .get_posts()?
+            .into_iter()
+            .filter_map(|path| self.parse_post(path, format).ok())
+            .collect();
+        Ok(posts)
+    }
+
+    /// Reads and parses a post. Its format follows its extension, or the
+    /// notebook's format if the extension is unknown.
+    pub fn read_post<P: AsRef<Path>>(&self, path: P) -> Result<Post, Error> {
+        self.parse_post(path, self.read_config()?.post_format())
+    }
+
+    fn parse_post<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<Post, Error> {
+        let contents = self.read_text(&path)?;
+        let format = Format::from_path(&path).unwrap_or(format);
+        Ok(Post::parse_as(path, &contents, format))
+    }
+
+    fn absolute_paths(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self
+            .get_posts()?
             .iter()
             .map(|p| self.path.join(p))
-            .collect::<Vec<PathBuf>>()
+            .collect::<Vec<PathBuf>>())
+    }
+
+    /// Returns the paths of posts containing `query`. Skips posts that can't be read.
+    pub fn search(&self, query: &str) -> Result<Vec<PathBuf>, Error> {
+        Ok(self
+            .absolute_paths()?
+            .into_iter()
+            .filter(|path| match self.read_text(path) {
+                Ok(text) => search::matches(&text, query),
+                Err(_) => false,
+            })
+            .collect())
+    }
+
+    /// Counts the tags in all posts. Skips posts that can't be read.
+    pub fn tags(&self) -> Result<search::Tags, Error> {
+        let mut tags = HashMap::new();
+        for post in self.read_posts()? {
+            for tag in post.format.handler().tags(&post.body) {
+                tags.entry(tag).and_modify(|t| *t += 1).or_insert(1);
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Adds a hashtag to the end of a post, unless it already has the tag.
+    /// Returns whether the post was changed.
+    pub fn tag_post<P: AsRef<Path>>(&self, path: P, tag: &str) -> Result<bool, Error> {
+        let path = path.as_ref();
+        let tag = tag.trim_start_matches('#');
+        if !search::is_tag(tag) {
+            return Err(Error::InvalidTag(tag.to_string()));
+        }
+        if self.read_post(path)?.tags().contains(&tag.to_lowercase()) {
+            return Ok(false);
+        }
+
+        let text = search::add_tag(&self.read_text(path)?, tag);
+        history::snapshot(self, path)?;
+        self.write_text(path, &text)?;
+        self.commit(&format!("Tag post {} with #{}", path.display(), tag))?;
+        Ok(true)
+    }
+
+    /// Copies a file into the notebook's attachments, unless the same file
+    /// is already there. The attachment is committed with the next change,
+    /// such as the post that links to it.
+    pub fn attach<P: AsRef<Path>>(&self, file: P) -> Result<Attachment, Error> {
+        let file = file.as_ref();
+        let attachment = Attachment::for_file(file)?;
+
+        let path = self.path.join(attachment.path());
+        if !path.exists() {
+            let dir = self.path.join(attachments::DIR);
+            fs::create_dir_all(&dir).context("create", &dir)?;
+            self.write_bytes(&path, &fs::read(file).context("read", file)?)?;
+        }
+        attachments::add_to_index(self, &attachment)?;
+        Ok(attachment)
+    }
+
+    /// Reads a file in the notebook, decrypting it if needed.
+    /// `path` can be absolute or relative to the notebook.
+    pub fn read_bytes<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error> {
+        let path = self.path.join(path);
+        let contents = fs::read(&path).context("read", &path)?;
+        if crypto::is_encrypted(&contents) {
+            self.key.as_ref().ok_or(Error::Locked)?.decrypt(&contents)
+        } else {
+            Ok(contents)
+        }
+    }
+
+    /// Reads a text file in the notebook, decrypting it if needed.
+    /// `path` can be absolute or relative to the notebook.
+    pub fn read_text<P: AsRef<Path>>(&self, path: P) -> Result<String, Error> {
+        let contents = self.read_bytes(&path)?;
+        String::from_utf8(contents).map_err(|_| Error::File {
+            action: "read",
+            path: self.path.join(path),
+            source: std::io::ErrorKind::InvalidData.into(),
+        })
+    }
+
+    /// Writes a file in the notebook, encrypting it if the notebook is encrypted.
+    /// `path` can be absolute or relative to the notebook.
+    pub fn write_bytes<P: AsRef<Path>>(&self, path: P, contents: &[u8]) -> Result<(), Error> {
+        let path = self.path.join(path);
+        if self.read_config()?.encrypted {
+            let key = self.key.as_ref().ok_or(Error::Locked)?;
+            crypto::write_private(&path, &key.encrypt(contents)?)
+        } else {
+            fs::write(&path, contents).context("write", &path)
+        }
+    }
+
+    /// Writes a text file in the notebook, encrypting it if the notebook is encrypted.
+    /// `path` can be absolute or relative to the notebook.
+    pub fn write_text<P: AsRef<Path>>(&self, path: P, text: &str) -> Result<(), Error> {
+        self.write_bytes(path, text.as_bytes())
     }
 
-    pub fn search(&self, query: &str) -> Vec<PathBuf> {
-        search::search_files(&self.absolute_paths(), query)
+    /// The files besides posts whose contents are encrypted in an encrypted
+    /// notebook: drafts, earlier versions of posts and attachments
+    fn stored_files(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut files = self.list_drafts()?;
+        files.extend(history::objects(self)?);
+        files.extend(
+            attachments::files(self)?
+                .into_iter()
+                .map(|name| Path::new(attachments::DIR).join(name)),
+        );
+        Ok(files)
+    }
+
+    /// Whether [`Notebook::unlock`] needs a passphrase
+    pub fn needs_passphrase(&self) -> Result<bool, Error> {
+        let config = self.read_config()?;
+        Ok(config.encrypted && config.key_file.is_none() && self.key.is_none())
+    }
+
+    /// Loads the key of an encrypted notebook so its posts can be read and written.
+    /// Does nothing for notebooks that aren't encrypted.
+    pub fn unlock(&mut self, passphrase: Option<&str>) -> Result<(), Error> {
+        let config = self.read_config()?;
+        if !config.encrypted || self.key.is_some() {
+            return Ok(());
+        }
+
+        self.key = Some(match (config.key_file, passphrase) {
+            (Some(key_file), _) => crypto::Key::load(Path::new(&*shellexpand::tilde(&key_file)))?,
+            (None, Some(passphrase)) => {
+                crypto::Key::load_with_passphrase(&self.path.join("key.age"), passphrase)?
+            }
+            (None, None) => return Err(Error::Locked),
+        });
+        Ok(())
+    }
+
+    /// Encrypts every post and draft in the notebook with a new key. The key is protected by
+    /// `passphrase`, or stored in `key_file` (which is created if it doesn't exist).
+    /// Posts are renamed by their time, as their names come from their text.
+    pub fn encrypt(
+        &mut self,
+        passphrase: Option<&str>,
+        key_file: Option<&Path>,
+    ) -> Result<(), Error> {
+        if self.read_config()?.encrypted {
+            return Err(Error::Crypto(format!("{} is already encrypted", self.id)));
+        }
+
+        let key = match (key_file, passphrase) {
+            (Some(key_file), _) if key_file.exists() => crypto::Key::load(key_file)?,
+            (Some(key_file), _) => {
+                let key = crypto::Key::generate();
+                key.save(key_file)?;
+                key
+            }
+            (None, Some(passphrase)) => {
+                let key = crypto::Key::generate();
+                key.save_with_passphrase(&self.path.join("key.age"), passphrase)?;
+                key
+            }
+            (None, None) => {
+                return Err(Error::Crypto(
+                    "a passphrase or key file is needed".to_string(),
+                ))
+            }
+        };
+
+        // The key file has to be found from any directory
+        let key_file = match key_file {
+            Some(path) => Some(fs::canonicalize(path).context("read", path)?),
+            None => None,
+        };
+
+        // The notebook is marked as encrypted first. Encrypted and plain files
+        // can both be read, so a failure part way doesn't lose anything, while
+        // the other order would leave plain text in a notebook that claims not to.
+        self.set_config(&[
+            ("encrypted", Some(Value::Boolean(true))),
+            (
+                "key_file",
+                key_file.map(|p| Value::String(p.to_string_lossy().into_owned())),
+            ),
+        ])?;
+        self.key = Some(key);
+
+        for path in self.stored_files()? {
+            let contents = self.read_bytes(&path)?;
+            self.write_bytes(&path, &contents)?;
+        }
+
+        let config = self.read_config()?;
+        let mut posts = self.get_posts()?;
+        for i in 0..posts.len() {
+            let old = posts[i].clone();
+            let old_path = self.path.join(&old);
+            let contents = self.read_bytes(&old_path)?;
+            let text = String::from_utf8_lossy(&contents);
+            let time = Post::parse_as(&old, &text, config.post_format())
+                .timestamp()
+                .unwrap_or_else(|| config.now());
+            let dt = Timestamp::from_datetime(&time);
+            let path = self.new_post_path(&config, "", &dt, Some(&old_path));
+            self.write_bytes(&path, &contents)?;
+            if path == old_path {
+                continue;
+            }
+
+            // unwrap is okay because the path was made from post_path like in `write_post`
+            let new = PathBuf::from(path.file_name().unwrap());
+            posts[i] = new.clone();
+            self.write_index(&posts)?;
+            fs::remove_file(&old_path).context("remove", &old_path)?;
+            history::rename(self, &old, &new)?;
+            undo::rename_post(&self.id, &old, &new)?;
+        }
+
+        self.commit("Encrypt notebook")
     }
 
-    pub fn tags(&self) -> search::Tags {
-        search::tags(&self.absolute_paths())
+    /// Decrypts every post and draft of an unlocked notebook and stops encrypting new ones.
+    pub fn decrypt(&mut self) -> Result<(), Error> {
+        if !self.read_config()?.encrypted {
+            return Err(Error::Crypto(format!("{} isn't encrypted", self.id)));
+        }
+
+        let mut posts = self.get_posts()?;
+        posts.extend(self.stored_files()?);
+        let contents = posts
+            .iter()
+            .map(|path| self.read_bytes(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (path, contents) in posts.iter().zip(contents) {
+            let path = self.path.join(path);
+            fs::write(&path, contents).context("write", &path)?;
+        }
+
+        self.set_config(&[("encrypted", None), ("key_file", None)])?;
+        let key_path = self.path.join("key.age");
+        if key_path.exists() {
+            fs::remove_file(&key_path).context("remove", &key_path)?;
+        }
+        self.key = None;
+        self.commit("Decrypt notebook")
+    }
+}
+
+/// Notebook ids name a directory in the root, and can't be hidden.
+fn check_id(id: &str) -> Result<(), Error> {
+    if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+        return Err(Error::InvalidName(id.to_string()));
+    }
+    Ok(())
+}
+
+fn dir_size(path: &Path) -> Result<u64, Error> {
+    let mut size = 0;
+    for entry in fs::read_dir(path).context("read", path)? {
+        let entry = entry.context("read", path)?;
+        let metadata = entry.metadata().context("read", entry.path())?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
     }
+    Ok(size)
 }
 
+/// Returns the notebooks in the root, leaving out archived ones.
 pub fn list() -> Result<Vec<Notebook>, Error> {
+    Ok(list_all()?
+        .into_iter()
+        .filter(|notebook| !notebook.read_config().is_ok_and(|c| c.archived))
+        .collect())
+}
+
+/// Returns every notebook in the root, including archived ones.
+pub fn list_all() -> Result<Vec<Notebook>, Error> {
     let root = config::get()?.root;
-    let dir = fs::read_dir(root)?;
+    // The root is only created along with the first notebook
+    let dir = match fs::read_dir(&root) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("read", root),
+    };
 
     let notebooks = dir
         .filter_map(|entry| match entry {
@@ -208,3 +948,82 @@ pub fn list() -> Result<Vec<Notebook>, Error> {
 
     Ok(notebooks)
 }
+
+/// Creates a notebook for a test. The tests of a run share a root in the
+/// temporary directory, so each needs a notebook id of its own.
+#[cfg(test)]
+pub(crate) fn test_notebook(id: &str, config: Config) -> Notebook {
+    static ENV: std::sync::Once = std::sync::Once::new();
+    ENV.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("jw-test-{}", std::process::id()));
+        for (key, sub) in [
+            ("XDG_CONFIG_HOME", "config"),
+            ("XDG_DATA_HOME", "data"),
+            ("XDG_CACHE_HOME", "cache"),
+        ] {
+            std::env::set_var(key, dir.join(sub));
+        }
+        for key in ["GIT_AUTHOR_NAME", "GIT_COMMITTER_NAME"] {
+            std::env::set_var(key, "jw");
+        }
+        for key in ["GIT_AUTHOR_EMAIL", "GIT_COMMITTER_EMAIL"] {
+            std::env::set_var(key, "jw@example.com");
+        }
+    });
+    Notebook::generate_with(id, config).unwrap()
+}
+
+#[test]
+fn encrypt_renames_posts() {
+    let mut notebook = test_notebook("encrypt", Config::new("encrypt"));
+    let time = |s| DateTime::parse_from_rfc3339(s).unwrap();
+    let first = notebook
+        .post_at("Dear diary\n", time("2022-05-11T15:15:56+02:00"))
+        .unwrap();
+    let first = first.strip_prefix(&notebook.path).unwrap().to_owned();
+    history::snapshot(&notebook, &first).unwrap();
+    notebook
+        .post_at("Secret plans\n", time("2022-05-12T08:00:00+02:00"))
+        .unwrap();
+
+    // A post that can't be read stops encryption part way
+    let mut posts = notebook.get_posts().unwrap();
+    posts.insert(1, PathBuf::from("bad.md"));
+    notebook.write_index(&posts).unwrap();
+    fs::create_dir(notebook.path.join("bad.md")).unwrap();
+
+    let key_file = notebook.path.with_extension("key");
+    let e = notebook.encrypt(None, Some(&key_file)).unwrap_err();
+    assert!(matches!(&e, Error::File { path, .. } if path.ends_with("bad.md")));
+    assert!(notebook.read_config().unwrap().encrypted);
+    let posts = notebook.get_posts().unwrap();
+    assert_eq!(posts[0], Path::new("2022-05-11-151556.md"));
+    assert_eq!(posts[2], Path::new("2022-05-12-Secret-plans.md"));
+    assert!(!notebook.path.join(&first).exists());
+    assert_eq!(history::list(&notebook, &posts[0]).unwrap().len(), 1);
+    for post in [&posts[0], &posts[2]] {
+        assert!(notebook.read_post(post).unwrap().body.ends_with("\n"));
+    }
+    let posts = vec![posts[0].clone(), posts[2].clone()];
+    notebook.write_index(&posts).unwrap();
+
+    // Once the post is gone, it can be decrypted and encrypted again
+    notebook.decrypt().unwrap();
+    notebook.encrypt(None, Some(&key_file)).unwrap();
+    assert_eq!(
+        notebook.get_posts().unwrap(),
+        vec![
+            PathBuf::from("2022-05-11-151556.md"),
+            PathBuf::from("2022-05-12-080000.md")
+        ]
+    );
+    let index = fs::read_to_string(notebook.path.join("posts.txt")).unwrap();
+    assert!(!index.contains("Secret"));
+    assert_eq!(
+        notebook.read_post("2022-05-12-080000.md").unwrap().body,
+        "Secret plans\n"
+    );
+
+    fs::remove_dir_all(&notebook.path).unwrap();
+    fs::remove_file(key_file).unwrap();
+}
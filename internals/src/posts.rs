@@ -0,0 +1,121 @@
+//! Parsed representation of individual posts
+//!
+//! Posts are stored as plain files. Markdown posts have an optional front
+//! matter block, and other [formats](crate::formats) their own metadata:
+//! ```text
+//! ---
+//! timestamp: 2022-05-11T15:15:56.680150936+02:00
+//! ---
+//! The text of the post
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeZone};
+
+use crate::formats::Format;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Post {
+    /// The path of the post, relative to its notebook
+    pub path: PathBuf,
+    pub format: Format,
+    /// Key-value pairs from the front matter
+    pub metadata: BTreeMap<String, String>,
+    /// The text of the post without front matter
+    pub body: String,
+}
+
+impl Post {
+    /// Splits the front matter from the body of a post, in the format its
+    /// extension suggests or Markdown.
+    pub fn parse<P: AsRef<Path>>(path: P, contents: &str) -> Self {
+        let format = Format::from_path(&path).unwrap_or_default();
+        Self::parse_as(path, contents, format)
+    }
+
+    /// Splits the metadata from the body of a post written in `format`.
+    pub fn parse_as<P: AsRef<Path>>(path: P, contents: &str, format: Format) -> Self {
+        let (metadata, body) = format.handler().split(contents);
+        Self {
+            path: path.as_ref().to_owned(),
+            format,
+            metadata,
+            body: body.to_string(),
+        }
+    }
+
+    /// Returns the time of the post from the front matter, falling back to a
+    /// `YYYY-MM-DD` prefix of the filename.
+    pub fn timestamp(&self) -> Option<DateTime<FixedOffset>> {
+        if let Some(ts) = self.metadata.get("timestamp") {
+            if let Ok(dt) = DateTime::parse_from_rfc3339(ts) {
+                return Some(dt);
+            }
+        }
+
+        let name = self.path.file_name()?.to_string_lossy();
+        let date = NaiveDate::parse_from_str(name.get(..10)?, "%Y-%m-%d").ok()?;
+        let dt = Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .earliest()?;
+        Some(dt.into())
+    }
+
+    /// The first non-empty line of the post, or the filename if there is none.
+    pub fn title(&self) -> String {
+        match self.format.handler().title(&self.body) {
+            Some(title) => title,
+            None => self.slug(),
+        }
+    }
+
+    /// The filename of the post without its extension
+    pub fn slug(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Renders the post's body to HTML.
+    pub fn to_html(&self) -> String {
+        self.format.handler().to_html(&self.body)
+    }
+
+    /// The hashtags used in the post, in order of appearance
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = Vec::new();
+        for tag in self.format.handler().tags(&self.body) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+}
+
+#[test]
+fn parse_front_matter() {
+    let post = Post::parse(
+        "2022-05-11-hello.md",
+        "---\ntimestamp: 2022-05-11T15:15:56.680150936+02:00\n---\n# Hello #World\nText #rust\n",
+    );
+    assert_eq!(post.body, "# Hello #World\nText #rust\n");
+    assert_eq!(post.title(), "Hello #World");
+    assert_eq!(post.tags(), vec!["world", "rust"]);
+    assert_eq!(
+        post.timestamp().unwrap().to_rfc3339(),
+        "2022-05-11T15:15:56.680150936+02:00"
+    );
+
+    let post = Post::parse("2022-05-11-hello.md", "No metadata\n");
+    assert_eq!(post.body, "No metadata\n");
+    assert_eq!(
+        post.timestamp().unwrap().date_naive(),
+        NaiveDate::from_ymd_opt(2022, 5, 11).unwrap()
+    );
+}
@@ -0,0 +1,98 @@
+//! Quick capture of one-line posts
+//!
+//! Each line is a post of its own, unless the notebook has `daily_file` set.
+//! Then the lines of a day are collected in one post titled with the date:
+//! ```text
+//! 2022-05-11
+//!
+//! - 09:12 Coffee with Sam
+//! - 15:40 Finished the report #work
+//! ```
+//! Adding a line saves the earlier version of the daily post, like editing
+//! it. `jw undo` can't take back a single line, so once a line has been
+//! added the daily post is no longer in the undo journal; `jw restore`
+//! brings back an earlier version instead.
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::{history, undo, Error, Notebook};
+
+/// Posts a line, or adds it to today's daily post. Returns the path of the post.
+pub fn capture(notebook: &Notebook, line: &str) -> Result<PathBuf, Error> {
+    let config = notebook.read_config()?;
+    if !config.daily_file {
+        return notebook.post(&format!("{}\n", line.trim()));
+    }
+
+    let now = config.now();
+    let entry = entry(now, line);
+    match daily_post(notebook, now.date_naive())? {
+        Some(path) => {
+            let mut text = notebook.read_text(&path)?.trim_end().to_string();
+            text.push('\n');
+            text.push_str(&entry);
+            history::snapshot(notebook, &path)?;
+            notebook.write_text(&path, &text)?;
+            // Undoing the post now would throw away the lines added since
+            undo::forget(&undo::Operation::Post {
+                notebook: notebook.id.clone(),
+                path: path.clone(),
+            })?;
+
+            match notebook.commit(&format!("Add to post {}", path.display())) {
+                Ok(()) => Ok(notebook.path.join(path)),
+                Err(e) => Err(Error::Uncommitted {
+                    path: notebook.path.join(path),
+                    source: Box::new(e),
+                }),
+            }
+        }
+        None => notebook.post(&format!("{}\n\n{}", title(now.date_naive()), entry)),
+    }
+}
+
+/// A line of a daily post
+fn entry(time: DateTime<FixedOffset>, line: &str) -> String {
+    format!("- {} {}\n", time.format("%H:%M"), line.trim())
+}
+
+fn title(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+/// Finds the daily post of `day`, if there already is one. Only posts
+/// changed in the last day can have been written today, so older ones
+/// aren't read.
+fn daily_post(notebook: &Notebook, day: NaiveDate) -> Result<Option<PathBuf>, Error> {
+    let since = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+    for path in notebook.get_posts()?.into_iter().rev() {
+        let modified = fs::metadata(notebook.path.join(&path)).and_then(|m| m.modified());
+        if !modified.is_ok_and(|time| time >= since) {
+            continue;
+        }
+        let post = match notebook.read_post(&path) {
+            Ok(post) => post,
+            Err(_) => continue,
+        };
+        if post.title() == title(day) && post.timestamp().is_some_and(|dt| dt.date_naive() == day) {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+#[test]
+fn daily_entries() {
+    let time = DateTime::parse_from_rfc3339("2022-05-11T09:12:00+02:00").unwrap();
+    assert_eq!(
+        entry(time, " Coffee with Sam \n"),
+        "- 09:12 Coffee with Sam\n"
+    );
+    assert_eq!(title(time.date_naive()), "2022-05-11");
+}
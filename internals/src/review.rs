@@ -0,0 +1,179 @@
+//! Resurfacing old posts, at random or on a spaced-repetition schedule
+//!
+//! Reviews are scheduled with the SM-2 algorithm: a post that was easy to
+//! recall comes back after longer and longer intervals, one that wasn't comes
+//! back the next day. The schedule of each notebook is kept in `review.toml`
+//! next to its `posts.txt`:
+//! ```toml
+//! ["2022-05-11-hello.md"]
+//! due = "2022-05-17"
+//! interval = 6
+//! ease = 2.5
+//! repetitions = 2
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Duration, NaiveDate};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Context, Error, Notebook, Post};
+
+/// How well a post was remembered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    /// The grade on SM-2's scale from 0 to 5
+    fn quality(self) -> f64 {
+        match self {
+            Grade::Again => 1.0,
+            Grade::Hard => 3.0,
+            Grade::Good => 4.0,
+            Grade::Easy => 5.0,
+        }
+    }
+}
+
+/// The review schedule of a post
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Card {
+    pub due: NaiveDate,
+    /// Days until the next review
+    pub interval: i64,
+    pub ease: f64,
+    /// Reviews in a row that weren't graded `Again`
+    pub repetitions: u32,
+}
+
+impl Card {
+    fn new(today: NaiveDate) -> Self {
+        Self {
+            due: today,
+            interval: 0,
+            ease: 2.5,
+            repetitions: 0,
+        }
+    }
+
+    /// Schedules the next review after grading this one.
+    pub fn grade(&mut self, grade: Grade, today: NaiveDate) {
+        let q = grade.quality();
+        if grade == Grade::Again {
+            self.repetitions = 0;
+            self.interval = 1;
+        } else {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease).round() as i64,
+            };
+            self.repetitions += 1;
+        }
+        self.ease = (self.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(1.3);
+        self.due = today + Duration::days(self.interval);
+    }
+}
+
+/// The review schedule of a notebook
+pub struct Reviews {
+    path: PathBuf,
+    pub cards: BTreeMap<String, Card>,
+}
+
+impl Reviews {
+    /// Reads the schedule of a notebook. Notebooks that were never reviewed
+    /// have an empty one.
+    pub fn load(notebook: &Notebook) -> Result<Self, Error> {
+        let path = notebook.path.join("review.toml");
+        let cards = match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).map_err(|source| Error::Config {
+                path: path.clone(),
+                source,
+            })?,
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).context("read", path),
+        };
+        Ok(Self { path, cards })
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        fs::write(&self.path, toml::to_string(&self.cards)?).context("write", &self.path)
+    }
+
+    /// Returns the posts to review today: those that are due, oldest first,
+    /// followed by up to `new` posts that were never reviewed.
+    pub fn due(&self, posts: &[PathBuf], today: NaiveDate, new: usize) -> Vec<PathBuf> {
+        let mut due: Vec<(NaiveDate, &PathBuf)> = posts
+            .iter()
+            .filter_map(|post| {
+                let card = self.cards.get(post.to_string_lossy().as_ref())?;
+                Some((card.due, post)).filter(|(due, _)| *due <= today)
+            })
+            .collect();
+        due.sort_by_key(|(due, _)| *due);
+
+        let unseen = posts
+            .iter()
+            .filter(|post| !self.cards.contains_key(post.to_string_lossy().as_ref()))
+            .take(new);
+        due.into_iter()
+            .map(|(_, post)| post)
+            .chain(unseen)
+            .cloned()
+            .collect()
+    }
+
+    /// Records a review of a post, returning when it is due next.
+    pub fn grade(&mut self, post: &Path, grade: Grade, today: NaiveDate) -> NaiveDate {
+        let card = self
+            .cards
+            .entry(post.to_string_lossy().into_owned())
+            .or_insert_with(|| Card::new(today));
+        card.grade(grade, today);
+        card.due
+    }
+}
+
+/// Picks a random post, only from posts with `tag` if it is given.
+pub fn random(notebook: &Notebook, tag: Option<&str>) -> Result<Option<Post>, Error> {
+    let tag = tag.map(|tag| tag.trim_start_matches('#').to_lowercase());
+    let mut posts: Vec<Post> = notebook
+        .read_posts()?
+        .into_iter()
+        .filter(|post| tag.as_ref().is_none_or(|tag| post.tags().contains(tag)))
+        .collect();
+    if posts.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(posts.swap_remove(fastrand::usize(..posts.len()))))
+}
+
+#[test]
+fn schedule() {
+    let today = NaiveDate::from_ymd_opt(2022, 5, 11).unwrap();
+    let mut card = Card::new(today);
+
+    card.grade(Grade::Good, today);
+    assert_eq!((card.interval, card.repetitions), (1, 1));
+    card.grade(Grade::Good, today);
+    assert_eq!(card.interval, 6);
+    card.grade(Grade::Easy, today);
+    assert_eq!(card.interval, 15);
+    assert_eq!(card.due, NaiveDate::from_ymd_opt(2022, 5, 26).unwrap());
+    assert!(card.ease > 2.5);
+
+    card.grade(Grade::Again, today);
+    assert_eq!((card.interval, card.repetitions), (1, 0));
+    assert!(card.ease < 2.6);
+}
@@ -0,0 +1,176 @@
+//! Scratch files for posts being written in an editor
+//!
+//! Scratch files live in `drafts/<notebook>/` under the cache directory and
+//! can only be read by the current user. They are removed once the post is
+//! saved, so any that are left over hold text from an editor or program that
+//! crashed, and can be recovered. Text kept for an encrypted notebook is
+//! encrypted like its posts.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+use crate::{config, crypto, Context, Error, Notebook};
+
+const TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scratch {
+    /// The notebook the text is meant for
+    pub notebook: String,
+    pub path: PathBuf,
+    pub created: DateTime<Local>,
+}
+
+impl Scratch {
+    /// Creates an empty scratch file for a notebook, with the extension `ext`
+    /// (such as `.md`) so editors can pick the right syntax.
+    pub fn create(notebook: &str, ext: &str) -> Result<Self, Error> {
+        let created = Local::now();
+        let path = create_file(&notebook_dir(notebook)?, &created, ext)?;
+        Ok(Self {
+            notebook: notebook.to_string(),
+            path,
+            created,
+        })
+    }
+
+    /// Reads the text, decrypting it if it was encrypted by [`Scratch::keep`].
+    pub fn text(&self, notebook: &Notebook) -> Result<String, Error> {
+        notebook.read_text(&self.path)
+    }
+
+    /// Keeps the text for recovery when it couldn't be saved. For an encrypted
+    /// notebook it is encrypted, so it isn't left readable in the cache.
+    pub fn keep(&self, notebook: &Notebook) -> Result<(), Error> {
+        let contents = fs::read(&self.path).context("read", &self.path)?;
+        if notebook.read_config()?.encrypted && !crypto::is_encrypted(&contents) {
+            notebook.write_bytes(&self.path, &contents)?;
+        }
+        Ok(())
+    }
+
+    /// Decrypts text kept by [`Scratch::keep`] so it can be edited again.
+    pub fn restore(&self, notebook: &Notebook) -> Result<(), Error> {
+        let contents = fs::read(&self.path).context("read", &self.path)?;
+        if crypto::is_encrypted(&contents) {
+            self.write(&self.text(notebook)?)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the contents of the scratch file, such as with a template.
+    pub fn write(&self, text: &str) -> Result<(), Error> {
+        fs::write(&self.path, text).context("write", &self.path)
+    }
+
+    /// Removes the scratch file once its text is saved or no longer wanted.
+    pub fn discard(self) -> Result<(), Error> {
+        fs::remove_file(&self.path).context("remove", &self.path)
+    }
+
+    fn from_path(notebook: &str, path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy();
+        let stamp = NaiveDateTime::parse_from_str(name.get(..15)?, TIME_FORMAT).ok()?;
+        Some(Self {
+            notebook: notebook.to_string(),
+            path: path.to_owned(),
+            created: Local.from_local_datetime(&stamp).earliest()?,
+        })
+    }
+}
+
+fn drafts_dir() -> Result<PathBuf, Error> {
+    config::get_cache_path(Some("drafts"))
+}
+
+fn notebook_dir(notebook: &str) -> Result<PathBuf, Error> {
+    Ok(drafts_dir()?.join(notebook))
+}
+
+/// Returns the scratch files left over for a notebook, or for all notebooks,
+/// oldest first. Empty scratch files hold nothing to recover and are skipped.
+pub fn list(notebook: Option<&str>) -> Result<Vec<Scratch>, Error> {
+    let dirs = match notebook {
+        Some(id) => vec![(id.to_string(), notebook_dir(id)?)],
+        None => match fs::read_dir(drafts_dir()?) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| (e.file_name().to_string_lossy().into_owned(), e.path()))
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+    };
+
+    let mut scratches = Vec::new();
+    for (id, dir) in dirs {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            if entry.metadata().map(|m| m.len() == 0).unwrap_or(true) {
+                continue;
+            }
+            if let Some(scratch) = Scratch::from_path(&id, &entry.path()) {
+                scratches.push(scratch);
+            }
+        }
+    }
+    scratches.sort_by_key(|s| s.created);
+    Ok(scratches)
+}
+
+/// Creates an empty file for decrypted text that is being edited, which only
+/// the current user can read. The caller removes it when done.
+pub fn create_temp(ext: &str) -> Result<PathBuf, Error> {
+    create_file(&config::get_cache_path(Some("edit"))?, &Local::now(), ext)
+}
+
+/// Creates an empty private file in `dir`, named after `created`.
+fn create_file(dir: &Path, created: &DateTime<Local>, ext: &str) -> Result<PathBuf, Error> {
+    create_private_dir(dir)?;
+    let stamp = created.format(TIME_FORMAT);
+    let mut n = 0;
+    loop {
+        let name = if n == 0 {
+            format!("{stamp}{ext}")
+        } else {
+            format!("{stamp}-{n}{ext}")
+        };
+        let path = dir.join(name);
+        match private_file(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e).context("create", &path),
+        }
+    }
+}
+
+fn create_private_dir(path: &Path) -> Result<(), Error> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(path).context("create", path)
+}
+
+/// Creates an empty file that only the current user can read. Fails if it exists.
+fn private_file(path: &Path) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?;
+    Ok(())
+}
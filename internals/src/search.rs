@@ -18,8 +18,8 @@ pub type Tags = HashMap<String, usize>;
 pub fn search_files(paths: &[PathBuf], query: &str) -> Vec<PathBuf> {
     let mut new_paths = Vec::new();
     for path in paths {
-        if let Ok(contents) = read_to_string(&path) {
-            if contents.to_lowercase().contains(&query.to_lowercase()) {
+        if let Ok(contents) = read_to_string(path) {
+            if matches(&contents, query) {
                 new_paths.push(path.clone());
             }
         }
@@ -27,32 +27,87 @@ pub fn search_files(paths: &[PathBuf], query: &str) -> Vec<PathBuf> {
     new_paths
 }
 
+/// Returns whether a text contains the query, ignoring case.
+pub fn matches(text: &str, query: &str) -> bool {
+    text.to_lowercase().contains(&query.to_lowercase())
+}
+
 /// Extracts the tags from all files and counts them.
 /// Skips IO errors.
 pub fn tags(paths: &[PathBuf]) -> Tags {
     let mut tags = HashMap::new();
     for path in paths {
         if let Ok(f) = File::open(path) {
-            for line in BufReader::new(f).lines().flatten() {
-                let mut line = line;
-                line.push('\n');
-                let mut in_tag = false;
-                let mut tag = String::new();
-                for c in line.chars() {
-                    if in_tag {
-                        if c == ' ' || c.is_ascii_punctuation() || c == '\n' {
-                            in_tag = false;
-                            tags.entry(tag.clone().to_lowercase()).and_modify(|t| *t += 1).or_insert(1);
-                            tag.clear();
-                        } else {
-                            tag.push(c);
-                        }
-                    } else if c == '#' {
-                        in_tag = true;
+            for line in BufReader::new(f).lines().map_while(Result::ok) {
+                for tag in extract_tags(&line) {
+                    tags.entry(tag).and_modify(|t| *t += 1).or_insert(1);
+                }
+            }
+        }
+    }
+    tags
+}
+
+/// Extracts every hashtag in a text, lowercased and in order of appearance.
+pub fn extract_tags(text: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    for line in text.lines() {
+        let mut line = line.to_string();
+        line.push('\n');
+        let mut in_tag = false;
+        let mut tag = String::new();
+        for c in line.chars() {
+            if in_tag {
+                if c == ' ' || c.is_ascii_punctuation() || c == '\n' {
+                    in_tag = false;
+                    if !tag.is_empty() {
+                        tags.push(tag.to_lowercase());
                     }
+                    tag.clear();
+                } else {
+                    tag.push(c);
                 }
+            } else if c == '#' {
+                in_tag = true;
             }
         }
     }
     tags
 }
+
+/// Whether `tag` can be written as a hashtag and read back the same
+pub fn is_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| !c.is_whitespace() && !c.is_ascii_punctuation())
+}
+
+/// Adds a hashtag to the end of a text: to its last line if that only has
+/// hashtags, or else on a line of its own.
+pub fn add_tag(text: &str, tag: &str) -> String {
+    let text = text.trim_end();
+    let last = text.lines().last().unwrap_or_default();
+    let only_tags = last
+        .split_whitespace()
+        .all(|word| word.len() > 1 && word.starts_with('#'));
+    if text.is_empty() {
+        format!("#{tag}\n")
+    } else if only_tags && !last.trim().is_empty() {
+        format!("{text} #{tag}\n")
+    } else {
+        format!("{text}\n\n#{tag}\n")
+    }
+}
+
+#[test]
+fn extract_from_text() {
+    assert_eq!(
+        extract_tags("# Heading\nSome #Rust and #jw-posts\n#end"),
+        vec!["rust", "jw", "end"]
+    );
+    assert_eq!(add_tag("Text\n", "rust"), "Text\n\n#rust\n");
+    assert_eq!(add_tag("Text\n#life\n", "rust"), "Text\n#life #rust\n");
+    assert_eq!(add_tag("# Heading", "rust"), "# Heading\n\n#rust\n");
+    assert!(is_tag("rust") && !is_tag("jw-posts") && !is_tag(""));
+}
@@ -0,0 +1,141 @@
+//! Statistics about the writing in a notebook
+//!
+//! [`Stats`] serializes to JSON, so the GUI can show the same numbers as `jw stats`.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate, Timelike};
+use serde_derive::Serialize;
+
+use crate::{Error, Notebook, Post};
+
+/// The characters of the heatmap, from no posts to the most posts in a day
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    pub posts: usize,
+    pub words: usize,
+    /// Words per post, rounded down
+    pub average_words: usize,
+    /// Posts written on each day of the week, starting with Monday
+    pub weekdays: [usize; 7],
+    /// Posts written in each hour of the day
+    pub hours: [usize; 24],
+    /// Days in a row with posts, up to today. Today counts once it has a post,
+    /// so a streak isn't broken before the day is over.
+    pub current_streak: usize,
+    pub longest_streak: usize,
+    /// The number of posts on each day that has any
+    pub days: BTreeMap<NaiveDate, usize>,
+}
+
+impl Stats {
+    /// Computes the statistics of `posts` as of `today`. Posts without a
+    /// date only count towards the number of posts and words.
+    pub fn new(posts: &[Post], today: NaiveDate) -> Self {
+        let mut stats = Stats {
+            posts: posts.len(),
+            ..Default::default()
+        };
+
+        for post in posts {
+            stats.words += post.body.split_whitespace().count();
+            if let Some(dt) = post.timestamp() {
+                stats.weekdays[dt.weekday().num_days_from_monday() as usize] += 1;
+                stats.hours[dt.hour() as usize] += 1;
+                *stats.days.entry(dt.date_naive()).or_insert(0) += 1;
+            }
+        }
+        stats.average_words = stats.words.checked_div(stats.posts).unwrap_or(0);
+
+        let mut streak = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for &day in stats.days.keys() {
+            streak = match previous {
+                Some(previous) if day - previous == Duration::days(1) => streak + 1,
+                _ => 1,
+            };
+            stats.longest_streak = stats.longest_streak.max(streak);
+            previous = Some(day);
+        }
+        if previous.is_some_and(|last| last == today || last == today - Duration::days(1)) {
+            stats.current_streak = streak;
+        }
+
+        stats
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders the posts of the last `weeks` weeks like GitHub's contribution
+    /// calendar: a row for each day of the week and a column for each week,
+    /// shaded by how many posts there are that day.
+    pub fn heatmap(&self, today: NaiveDate, weeks: usize) -> String {
+        let most = self.days.values().copied().max().unwrap_or(0).max(1);
+        let days_since_monday = today.weekday().num_days_from_monday() as i64;
+        let start = today - Duration::days(days_since_monday + 7 * (weeks as i64 - 1));
+
+        let mut rows = Vec::new();
+        for i in 0..7 {
+            // Like GitHub, only every other day is labelled
+            let label = match i {
+                0 => "Mon",
+                2 => "Wed",
+                4 => "Fri",
+                _ => "",
+            };
+            let mut row = format!("{:<4}", label);
+            for week in 0..weeks {
+                let day = start + Duration::days(7 * week as i64 + i as i64);
+                if day > today {
+                    break;
+                }
+                let count = self.days.get(&day).copied().unwrap_or(0);
+                // Any post at all gets at least the lightest shade
+                let shade = (count * (SHADES.len() - 1)).div_ceil(most);
+                row.push(SHADES[shade]);
+            }
+            rows.push(row.trim_end().to_string());
+        }
+        rows.join("\n")
+    }
+}
+
+/// Computes the statistics of a notebook's posts as of today.
+pub fn for_notebook(notebook: &Notebook) -> Result<Stats, Error> {
+    let today = notebook.read_config()?.now().date_naive();
+    Ok(Stats::new(&notebook.read_posts()?, today))
+}
+
+#[test]
+fn streaks_and_counts() {
+    let post = |date: &str, body: &str| Post::parse(format!("{date}-post.md"), body);
+    let posts = [
+        post("2022-05-01", "one two three"),
+        post("2022-05-02", "four"),
+        post("2022-05-03", "five six"),
+        post("2022-05-09", "seven eight"),
+        post("2022-05-10", ""),
+        post("2022-05-10", "nine"),
+    ];
+    let today = NaiveDate::from_ymd_opt(2022, 5, 11).unwrap();
+    let stats = Stats::new(&posts, today);
+
+    assert_eq!(stats.posts, 6);
+    assert_eq!(stats.words, 9);
+    assert_eq!(stats.average_words, 1);
+    assert_eq!(stats.longest_streak, 3);
+    assert_eq!(stats.current_streak, 2);
+    // 2022-05-01 was a Sunday
+    assert_eq!(stats.weekdays, [2, 3, 0, 0, 0, 0, 1]);
+
+    let later = NaiveDate::from_ymd_opt(2022, 5, 12).unwrap();
+    assert_eq!(Stats::new(&posts, later).current_streak, 0);
+
+    let heatmap = stats.heatmap(today, 2);
+    assert_eq!(heatmap.lines().count(), 7);
+    assert!(heatmap.starts_with("Mon ▒▒\n"));
+}
@@ -0,0 +1,118 @@
+//! Checklist items written in posts
+//!
+//! Any Markdown list item starting with a checkbox is a task:
+//! ```text
+//! - [ ] an open task
+//! - [x] a completed task
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::{history, Error, Notebook, Post};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    /// The number of the task in its notebook, counting from 1 in the order
+    /// of posts. It stays the same when tasks are completed.
+    pub id: usize,
+    /// The post the task is in, relative to the notebook
+    pub post: PathBuf,
+    /// The line of the task in the post file, counting from 0
+    pub line: usize,
+    pub text: String,
+    pub done: bool,
+    /// When the post was written
+    pub date: Option<DateTime<FixedOffset>>,
+}
+
+/// Splits a line into the text before the checkbox, whether it is checked,
+/// and the text of the task. Returns `None` for lines that aren't tasks.
+fn parse_line(line: &str) -> Option<(&str, bool, &str)> {
+    let item = line.trim_start();
+    let rest = item
+        .strip_prefix("- ")
+        .or_else(|| item.strip_prefix("* "))
+        .or_else(|| item.strip_prefix("+ "))?;
+    let done = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let prefix = &line[..line.len() - rest.len()];
+    Some((prefix, done, rest[3..].trim()))
+}
+
+/// Finds the tasks in the contents of a post. Their ids are left at 0.
+pub fn extract<P: AsRef<Path>>(path: P, contents: &str) -> Vec<Task> {
+    let date = Post::parse(&path, contents).timestamp();
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let (_, done, text) = parse_line(text)?;
+            Some(Task {
+                id: 0,
+                post: path.as_ref().to_owned(),
+                line,
+                text: text.to_string(),
+                done,
+                date,
+            })
+        })
+        .collect()
+}
+
+/// Returns the tasks in all posts of a notebook, in the order they were
+/// written. Posts that can't be read are skipped.
+pub fn list(notebook: &Notebook) -> Result<Vec<Task>, Error> {
+    let mut tasks = Vec::new();
+    for post in notebook.get_posts()? {
+        if let Ok(contents) = notebook.read_text(&post) {
+            tasks.extend(extract(&post, &contents));
+        }
+    }
+    for (i, task) in tasks.iter_mut().enumerate() {
+        task.id = i + 1;
+    }
+    Ok(tasks)
+}
+
+/// Checks the box of a task in its post.
+pub fn complete(notebook: &Notebook, task: &Task) -> Result<(), Error> {
+    let contents = notebook.read_text(&task.post)?;
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    let line = lines
+        .get_mut(task.line)
+        .filter(|line| parse_line(line).is_some_and(|(_, done, text)| !done && text == task.text))
+        .ok_or_else(|| Error::Task(format!("{} is no longer an open task", task.text)))?;
+    let (prefix, _, _) = parse_line(line).unwrap();
+    *line = format!("{}[x]{}", prefix, &line[prefix.len() + 3..]);
+
+    let mut text = lines.join("\n");
+    if contents.ends_with('\n') {
+        text.push('\n');
+    }
+    history::snapshot(notebook, &task.post)?;
+    notebook.write_text(&task.post, &text)?;
+    notebook.commit(&format!("Complete task in {}", task.post.display()))
+}
+
+#[test]
+fn find_tasks() {
+    let contents = "---\ntimestamp: 2022-05-11T15:15:56+02:00\n---\n\
+                    Today\n- [ ] call mom\n  * [x] buy milk\n- not a task\n- [] neither\n";
+    let tasks = extract("2022-05-11-today.md", contents);
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].text, "call mom");
+    assert_eq!(tasks[0].line, 4);
+    assert!(!tasks[0].done);
+    assert_eq!(tasks[1].text, "buy milk");
+    assert!(tasks[1].done);
+    assert_eq!(
+        tasks[0].date.unwrap().to_rfc3339(),
+        "2022-05-11T15:15:56+02:00"
+    );
+    assert_eq!(parse_line("  - [ ] x"), Some(("  - ", false, "x")));
+}
@@ -0,0 +1,165 @@
+//! Templates new posts start from
+//!
+//! Templates are plain files in a notebook's `templates/` directory, or in
+//! `templates/` in the config directory to be shared by every notebook. They
+//! are named without their extension, so `templates/daily.md` is `daily`.
+//!
+//! These placeholders are filled in when a post is started:
+//! * `{{date}}`: the date, such as `2022-05-11`
+//! * `{{time}}`: the time, such as `15:15`
+//! * `{{weekday}}`: the day of the week, such as `Wednesday`
+//! * `{{notebook}}`: the name of the notebook
+//! * `{{prompt}}`: a random line from `prompts.txt` in the notebook or config directory
+
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::{config, export, Context, Error, Notebook};
+
+const PLACEHOLDERS: &[&str] = &["date", "time", "weekday", "notebook", "prompt"];
+
+/// The directories templates are looked up in, the notebook's own first.
+fn dirs(notebook: &Notebook) -> Result<Vec<PathBuf>, Error> {
+    Ok(vec![
+        notebook.path.join("templates"),
+        config::get_path(Some("templates"))?,
+    ])
+}
+
+/// Finds the file of the template called `name`.
+fn find(notebook: &Notebook, name: &str) -> Result<PathBuf, Error> {
+    for dir in dirs(notebook)? {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.file_stem().is_some_and(|stem| stem == name) && path.is_file() {
+                return Ok(path);
+            }
+        }
+    }
+    Err(Error::TemplateNotFound(name.to_string()))
+}
+
+/// Returns the names of the templates available to a notebook.
+pub fn list(notebook: &Notebook) -> Result<Vec<String>, Error> {
+    let mut names = Vec::new();
+    for dir in dirs(notebook)? {
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if let (true, Some(stem)) = (path.is_file(), path.file_stem()) {
+                    names.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Picks a random prompt from the notebook's `prompts.txt`, or the global one.
+pub fn prompt(notebook: &Notebook) -> Result<Option<String>, Error> {
+    for path in [
+        notebook.path.join("prompts.txt"),
+        config::get_path(Some("prompts.txt"))?,
+    ] {
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).context("read", &path),
+        };
+        let prompts: Vec<&str> = contents
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect();
+        if !prompts.is_empty() {
+            return Ok(Some(prompts[fastrand::usize(..prompts.len())].to_string()));
+        }
+    }
+    Ok(None)
+}
+
+fn fill(template: &str, notebook: &str, now: DateTime<FixedOffset>, prompt: &str) -> String {
+    export::render(
+        template,
+        &[
+            ("date", &now.format("%Y-%m-%d").to_string()),
+            ("time", &now.format("%H:%M").to_string()),
+            ("weekday", &now.format("%A").to_string()),
+            ("notebook", notebook),
+            ("prompt", prompt),
+        ],
+    )
+}
+
+/// Returns the first placeholder in `template` that isn't filled in.
+fn unknown_placeholder(template: &str) -> Option<&str> {
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after.find("}}")?;
+        let key = &after[..end];
+        if !PLACEHOLDERS.contains(&key) {
+            return Some(key);
+        }
+        rest = &after[end + 2..];
+    }
+    None
+}
+
+/// Renders the template called `name` for a post written now, in the
+/// notebook's time zone.
+pub fn render(notebook: &Notebook, name: &str) -> Result<String, Error> {
+    let path = find(notebook, name)?;
+    let template = fs::read_to_string(&path).context("read", &path)?;
+    if let Some(key) = unknown_placeholder(&template) {
+        return Err(Error::InvalidTemplate(
+            name.to_string(),
+            format!("unknown placeholder {{{{{key}}}}}"),
+        ));
+    }
+    let config = notebook.read_config()?;
+    let prompt = if template.contains("{{prompt}}") {
+        prompt(notebook)?.unwrap_or_default()
+    } else {
+        String::new()
+    };
+    Ok(fill(&template, &config.name, config.now(), &prompt))
+}
+
+/// Renders the template chosen with `name`, or the notebook's default
+/// template. Returns `None` if neither is set.
+pub fn for_post(notebook: &Notebook, name: Option<&str>) -> Result<Option<String>, Error> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => match notebook.read_config()?.template {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+    };
+    render(notebook, &name).map(Some)
+}
+
+#[test]
+fn fill_placeholders() {
+    let now = DateTime::parse_from_rfc3339("2022-05-11T15:15:00+02:00").unwrap();
+    assert_eq!(
+        fill(
+            "# {{weekday}}, {{date}} {{time}}\n{{prompt}}\n\n#{{notebook}}",
+            "journal",
+            now,
+            "What went well?"
+        ),
+        "# Wednesday, 2022-05-11 15:15\nWhat went well?\n\n#journal"
+    );
+    assert_eq!(
+        unknown_placeholder("{{date}} {{ date }} {{"),
+        Some(" date ")
+    );
+}
@@ -1,5 +1,7 @@
 //! Helper structures and functions for working with chrono
-use chrono::Datelike;
+use std::fmt::Debug;
+
+use chrono::{DateTime, Datelike, TimeZone};
 
 #[derive(Debug)]
 pub struct Timestamp {
@@ -11,19 +13,20 @@ pub struct Timestamp {
 }
 
 impl Timestamp {
-    pub fn now() -> Self {
-        let dt = chrono::Local::now();
-
+    pub fn from_datetime<Tz: TimeZone>(dt: &DateTime<Tz>) -> Self
+    where
+        Tz::Offset: Debug,
+    {
         Self {
             day: dt.day(),
             month: dt.month(),
             year: dt.year(),
-            timestamp: format!("{:?}", chrono::offset::Local::now()),
+            timestamp: format!("{:?}", dt),
         }
     }
 }
 
 #[test]
 fn test_timestamp() {
-    println!("{:?}", Timestamp::now());
+    println!("{:?}", Timestamp::from_datetime(&chrono::Local::now()));
 }
@@ -0,0 +1,202 @@
+//! The trash that deleted posts and notebooks are moved to
+//!
+//! The trash is the `.trash` directory of the root. Every deleted item gets a
+//! directory of its own, named after when it was deleted, holding the item
+//! and a `trash.toml` that records where it came from:
+//! ```text
+//! .trash/20221105-093012-journal/
+//!     trash.toml
+//!     2022-11-04-Hello.md
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, Local};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{config, undo, Context, Error, Notebook};
+
+const TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Post,
+    Notebook,
+}
+
+/// Where a deleted item came from
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Origin {
+    pub kind: Kind,
+    pub notebook: String,
+    /// The path of a post relative to its notebook
+    #[serde(default)]
+    pub path: PathBuf,
+    /// The line of a post in `posts.txt`, so it goes back to the same place
+    #[serde(default)]
+    pub index: Option<usize>,
+    pub deleted: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The name of the entry's directory in the trash
+    pub id: String,
+    pub origin: Origin,
+}
+
+impl Entry {
+    fn dir(&self) -> Result<PathBuf, Error> {
+        Ok(trash_dir()?.join(&self.id))
+    }
+
+    fn write_origin(&self) -> Result<(), Error> {
+        let path = self.dir()?.join("trash.toml");
+        fs::write(&path, toml::to_string(&self.origin)?).context("write", &path)
+    }
+
+    /// The path of the deleted post or notebook in the trash
+    pub fn item(&self) -> Result<PathBuf, Error> {
+        let name = match self.origin.kind {
+            Kind::Post => self.origin.path.file_name().unwrap_or_default(),
+            Kind::Notebook => self.origin.notebook.as_ref(),
+        };
+        Ok(self.dir()?.join(name))
+    }
+
+    /// Moves the item back to where it was deleted from, returning its path.
+    /// Fails if something else is there now.
+    pub fn restore(self) -> Result<PathBuf, Error> {
+        let item = self.item()?;
+        let (path, notebook) = match self.origin.kind {
+            Kind::Post => {
+                let notebook = Notebook::load(&self.origin.notebook)?;
+                (notebook.path.join(&self.origin.path), Some(notebook))
+            }
+            Kind::Notebook => (config::get()?.root.join(&self.origin.notebook), None),
+        };
+        if path.exists() {
+            return Err(Error::Trash(format!("{} already exists", path.display())));
+        }
+        fs::rename(&item, &path).context("move", &item)?;
+
+        if let Some(notebook) = notebook {
+            notebook.insert_post(&self.origin.path, self.origin.index)?;
+            notebook.commit(&format!("Restore post {}", self.origin.path.display()))?;
+        }
+        let dir = self.dir()?;
+        fs::remove_dir_all(&dir).context("remove", &dir)?;
+        undo::forget(&undo::Operation::Delete { entry: self.id })?;
+        Ok(path)
+    }
+}
+
+fn trash_dir() -> Result<PathBuf, Error> {
+    Ok(config::get()?.root.join(".trash"))
+}
+
+/// Moves `item` into the trash, recording where it came from.
+pub(crate) fn put(item: &Path, origin: Origin) -> Result<Entry, Error> {
+    let trash = trash_dir()?;
+    let stamp = origin.deleted.format(TIME_FORMAT);
+    let mut id = format!("{stamp}-{}", origin.notebook);
+    let mut n = 1;
+    while trash.join(&id).exists() {
+        id = format!("{stamp}-{}-{n}", origin.notebook);
+        n += 1;
+    }
+
+    let entry = Entry { id, origin };
+    let dir = entry.dir()?;
+    fs::create_dir_all(&dir).context("create", &dir)?;
+    entry.write_origin()?;
+    fs::rename(item, entry.item()?).context("move", item)?;
+    Ok(entry)
+}
+
+pub(crate) fn origin(kind: Kind, notebook: &str) -> Origin {
+    Origin {
+        kind,
+        notebook: notebook.to_string(),
+        path: PathBuf::new(),
+        index: None,
+        deleted: Local::now().fixed_offset(),
+    }
+}
+
+/// Points the deleted posts of a renamed notebook to its new id, so they can
+/// still be restored.
+pub(crate) fn rename_notebook(old: &str, new: &str) -> Result<(), Error> {
+    for mut entry in list()? {
+        if entry.origin.kind == Kind::Post && entry.origin.notebook == old {
+            entry.origin.notebook = new.to_string();
+            entry.write_origin()?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the items in the trash, oldest first. Directories without a
+/// readable `trash.toml` are left out.
+pub fn list() -> Result<Vec<Entry>, Error> {
+    let trash = trash_dir()?;
+    let dir = match fs::read_dir(&trash) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("read", trash),
+    };
+
+    let mut entries: Vec<Entry> = dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = fs::read_to_string(entry.path().join("trash.toml")).ok()?;
+            Some(Entry {
+                id: entry.file_name().to_string_lossy().into_owned(),
+                origin: toml::from_str(&metadata).ok()?,
+            })
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.origin.deleted);
+    Ok(entries)
+}
+
+/// Finds an item in the trash by the name of its directory.
+pub fn get(id: &str) -> Result<Entry, Error> {
+    list()?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| Error::Trash(format!("{id} is no longer in the trash")))
+}
+
+/// Deletes everything in the trash for good, returning how many items there were.
+pub fn empty() -> Result<usize, Error> {
+    let entries = list()?;
+    for entry in &entries {
+        let dir = entry.dir()?;
+        fs::remove_dir_all(&dir).context("remove", &dir)?;
+        undo::forget(&undo::Operation::Delete {
+            entry: entry.id.clone(),
+        })?;
+    }
+    Ok(entries.len())
+}
+
+#[test]
+fn restore_after_rename() {
+    use crate::notebooks::{test_notebook, Config};
+
+    let mut notebook = test_notebook("trash-old", Config::new("trash-old"));
+    let post = notebook.post("Hello\n").unwrap();
+    let post = post.strip_prefix(&notebook.path).unwrap().to_owned();
+    let entry = notebook.delete_post(&post).unwrap();
+    notebook.rename("trash-new").unwrap();
+
+    let restored = get(&entry.id).unwrap().restore().unwrap();
+    assert_eq!(restored, notebook.path.join(&post));
+    assert_eq!(notebook.get_posts().unwrap(), vec![post]);
+    fs::remove_dir_all(&notebook.path).unwrap();
+}
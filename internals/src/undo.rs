@@ -0,0 +1,181 @@
+//! The journal of operations that `jw undo` can take back
+//!
+//! Every post and deletion is recorded as a line of JSON in `.journal` in the
+//! root, newest last. Undoing an operation removes it from the journal, so
+//! undoing again takes back the one before. Only the latest operations are kept.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{config, trash, Context, Error, Notebook};
+
+/// How many operations the journal remembers
+const LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "operation", rename_all = "lowercase")]
+pub enum Operation {
+    /// A post was written. Undoing it moves the post to the trash.
+    Post { notebook: String, path: PathBuf },
+    /// A post or notebook was moved to the trash. Undoing it restores it.
+    Delete { entry: String },
+}
+
+impl Operation {
+    /// Whether what the operation changed is still there to take back. A post
+    /// can be gone along with its notebook, and a deleted item can have been
+    /// removed from the trash or its notebook renamed.
+    fn can_revert(&self) -> bool {
+        match self {
+            Operation::Post { notebook, path } => {
+                Notebook::load(notebook).is_ok_and(|notebook| notebook.path.join(path).exists())
+            }
+            Operation::Delete { entry } => trash::get(entry).is_ok_and(|entry| {
+                entry.origin.kind == trash::Kind::Notebook
+                    || Notebook::load(&entry.origin.notebook).is_ok()
+            }),
+        }
+    }
+
+    /// Takes the operation back.
+    fn revert(&self) -> Result<(), Error> {
+        match self {
+            Operation::Post { notebook, path } => {
+                Notebook::load(notebook)?.trash_post(path)?;
+            }
+            Operation::Delete { entry } => {
+                trash::get(entry)?.restore()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn journal_path() -> Result<PathBuf, Error> {
+    Ok(config::get()?.root.join(".journal"))
+}
+
+fn read() -> Result<Vec<Operation>, Error> {
+    let path = journal_path()?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("read", path),
+    };
+    // Lines that can't be parsed, such as from newer versions, are skipped
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn write(operations: &[Operation]) -> Result<(), Error> {
+    let mut text = String::new();
+    for operation in operations {
+        text.push_str(&serde_json::to_string(operation)?);
+        text.push('\n');
+    }
+    let path = journal_path()?;
+    fs::write(&path, text).context("write", path)
+}
+
+/// Adds an operation to the journal.
+pub(crate) fn record(operation: Operation) -> Result<(), Error> {
+    let mut operations = read()?;
+    operations.push(operation);
+    let start = operations.len().saturating_sub(LIMIT);
+    write(&operations[start..])
+}
+
+/// Removes an operation from the journal once it can no longer be undone,
+/// such as a deletion whose item was restored from the trash by hand.
+pub(crate) fn forget(operation: &Operation) -> Result<(), Error> {
+    let mut operations = read()?;
+    let len = operations.len();
+    operations.retain(|op| op != operation);
+    if operations.len() < len {
+        write(&operations)?;
+    }
+    Ok(())
+}
+
+/// Points the operations on posts of a renamed notebook to its new id.
+pub(crate) fn rename_notebook(old: &str, new: &str) -> Result<(), Error> {
+    let mut operations = read()?;
+    let mut changed = false;
+    for operation in &mut operations {
+        if let Operation::Post { notebook, .. } = operation {
+            if notebook == old {
+                *notebook = new.to_string();
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        write(&operations)?;
+    }
+    Ok(())
+}
+
+/// Points the operation that wrote a post to its new path after it was renamed.
+pub(crate) fn rename_post(id: &str, old: &Path, new: &Path) -> Result<(), Error> {
+    let mut operations = read()?;
+    let mut changed = false;
+    for operation in &mut operations {
+        if let Operation::Post { notebook, path } = operation {
+            if notebook == id && path == old {
+                *path = new.to_owned();
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        write(&operations)?;
+    }
+    Ok(())
+}
+
+/// What [`undo`] did
+#[derive(Debug, Default)]
+pub struct Undone {
+    /// The operation that was taken back, or `None` if there was nothing to undo
+    pub operation: Option<Operation>,
+    /// Later operations that could no longer be taken back, and were dropped
+    /// from the journal, newest first
+    pub skipped: Vec<Operation>,
+}
+
+/// Takes back the latest operation in the journal that can still be taken back.
+pub fn undo() -> Result<Undone, Error> {
+    let mut operations = read()?;
+    let mut undone = Undone::default();
+    while let Some(operation) = operations.pop() {
+        if operation.can_revert() {
+            operation.revert()?;
+            undone.operation = Some(operation);
+            break;
+        }
+        undone.skipped.push(operation);
+    }
+    write(&operations)?;
+    Ok(undone)
+}
+
+#[test]
+fn journal_lines() {
+    let operation = Operation::Post {
+        notebook: "journal".to_string(),
+        path: PathBuf::from("2022-11-04-Hello.md"),
+    };
+    let line = serde_json::to_string(&operation).unwrap();
+    assert_eq!(
+        line,
+        r#"{"operation":"post","notebook":"journal","path":"2022-11-04-Hello.md"}"#
+    );
+    assert_eq!(serde_json::from_str::<Operation>(&line).unwrap(), operation);
+}